@@ -0,0 +1,202 @@
+use std::any::Any;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
+use crate::csp::{Csp, Directive, Nonce};
+use crate::{Origami, Rendered};
+
+/// Output buffer templates render into.
+///
+/// Dereferences to the underlying [`String`], so it can be used wherever a `&mut String` is expected.
+/// It also holds the context values provided during the render, and the content rendered into
+/// outlets by portals, which is inserted at the outlets by [`into_string`](Self::into_string),
+/// and the [`Csp`] sources of the inline blocks rendered so far.
+#[derive(Debug, Clone, Default)]
+pub struct Buffer {
+    s: String,
+    contexts: Vec<Arc<dyn Any + Send + Sync>>,
+    outlets: Vec<(Cow<'static, str>, usize)>,
+    portals: HashMap<Cow<'static, str>, String>,
+    once: HashSet<u64>,
+    csp: Csp,
+}
+
+impl Buffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            s: String::with_capacity(capacity),
+            ..Default::default()
+        }
+    }
+
+    pub fn into_string(self) -> String {
+        if self.outlets.is_empty() {
+            return self.s;
+        }
+        let len = self.s.len()
+            + self
+                .outlets
+                .iter()
+                .filter_map(|(name, _)| self.portals.get(name))
+                .map(String::len)
+                .sum::<usize>();
+        let mut s = String::with_capacity(len);
+        let mut pos = 0;
+        for (name, offset) in &self.outlets {
+            s.push_str(&self.s[pos..*offset]);
+            if let Some(content) = self.portals.get(name) {
+                s.push_str(content);
+            }
+            pos = *offset;
+        }
+        s.push_str(&self.s[pos..]);
+        s
+    }
+
+    /// Declares the outlet `name` at the current position.
+    pub fn outlet(&mut self, name: impl Into<Cow<'static, str>>) {
+        self.outlets.push((name.into(), self.s.len()));
+    }
+
+    /// Moves everything rendered since `start` into the outlet `name`.
+    ///
+    /// Content of outlets that are never declared is discarded.
+    pub fn portal(&mut self, name: impl Into<Cow<'static, str>>, start: usize) {
+        let content = self.s.split_off(start);
+        // Outlets cannot be declared inside a portal.
+        self.outlets.retain(|(_, offset)| *offset <= start);
+        self.portals
+            .entry(name.into())
+            .or_default()
+            .push_str(&content);
+    }
+
+    /// Makes `value` available to [`use_context`](Self::use_context) until the matching
+    /// [`pop_context`](Self::pop_context).
+    pub fn provide<T: Any + Send + Sync>(&mut self, value: T) {
+        self.contexts.push(Arc::new(value));
+    }
+
+    /// Returns `true` the first time it is called with `key` during the render.
+    pub fn once(&mut self, key: u64) -> bool {
+        self.once.insert(key)
+    }
+
+    /// Sources of the inline blocks rendered so far.
+    pub fn csp(&self) -> &Csp {
+        &self.csp
+    }
+
+    /// Adds `source`, e.g. the hash of an inline block, to the sources of the render.
+    pub fn csp_source(&mut self, directive: Directive, source: impl Into<Cow<'static, str>>) {
+        self.csp.add(directive, source);
+    }
+
+    /// Writes the `nonce` attribute with the [`Nonce`] of the context and adds it to the
+    /// sources of `directive`. Returns `false` if no nonce is provided, in which case the block
+    /// is allowed by its hash instead.
+    pub fn push_nonce(&mut self, directive: Directive) -> bool {
+        let Some(nonce) = self.use_context::<Nonce>() else {
+            return false;
+        };
+        self.s.push_str(" nonce=\"");
+        self.s
+            .push_str(&nonce.0.replace('&', "&amp;").replace('"', "&quot;"));
+        self.s.push('"');
+        self.csp.add(directive, format!("'nonce-{}'", nonce.0));
+        true
+    }
+
+    /// Removes the most recently provided context value.
+    pub fn pop_context(&mut self) {
+        self.contexts.pop();
+    }
+
+    /// Returns the most recently provided value of type `T`, if any.
+    pub fn use_context<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        self.contexts
+            .iter()
+            .rev()
+            .find_map(|value| value.clone().downcast::<T>().ok())
+    }
+
+    /// Empty buffer with the context and the `once` blocks of this one, to render into and
+    /// [`join`](Self::join) afterwards, or drop.
+    #[cfg(feature = "hot_reload")]
+    pub(crate) fn fork(&self) -> Self {
+        Self {
+            contexts: self.contexts.clone(),
+            once: self.once.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Appends a buffer [`fork`](Self::fork)ed from this one.
+    #[cfg(feature = "hot_reload")]
+    pub(crate) fn join(&mut self, other: Self) {
+        let offset = self.s.len();
+        self.s.push_str(&other.s);
+        self.outlets.extend(
+            other
+                .outlets
+                .into_iter()
+                .map(|(name, position)| (name, offset + position)),
+        );
+        for (name, content) in other.portals {
+            self.portals.entry(name).or_default().push_str(&content);
+        }
+        self.once.extend(other.once);
+        self.join_csp(&other.csp);
+    }
+
+    #[cfg(feature = "hot_reload")]
+    pub(crate) fn join_csp(&mut self, csp: &Csp) {
+        for directive in [Directive::Script, Directive::Style] {
+            for source in csp.sources(directive) {
+                self.csp.add(directive, source.clone());
+            }
+        }
+    }
+}
+
+impl Deref for Buffer {
+    type Target = String;
+
+    fn deref(&self) -> &Self::Target {
+        &self.s
+    }
+}
+
+impl DerefMut for Buffer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.s
+    }
+}
+
+impl From<Buffer> for String {
+    fn from(buffer: Buffer) -> Self {
+        buffer.into_string()
+    }
+}
+
+impl From<Buffer> for Origami {
+    fn from(buffer: Buffer) -> Self {
+        Origami(buffer.into_string())
+    }
+}
+
+impl From<Buffer> for Rendered {
+    fn from(mut buffer: Buffer) -> Self {
+        let csp = std::mem::take(&mut buffer.csp);
+        Rendered {
+            html: Origami(buffer.into_string()),
+            csp,
+        }
+    }
+}
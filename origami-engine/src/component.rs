@@ -0,0 +1,236 @@
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::csp::Directive;
+use crate::{Buffer, Origami, Rendered};
+
+/// A component that can be rendered at runtime, e.g. from a list of trait objects.
+///
+/// Templates render any `Component` with `@widget;`, and components defined with
+/// [`comp!`](crate::comp) are turned into one with `name!(dyn, ..props)`.
+pub trait Component {
+    fn render_to(&self, out: &mut Buffer);
+
+    fn render(&self) -> Origami {
+        let mut out = Buffer::new();
+        self.render_to(&mut out);
+        Origami::from(out)
+    }
+
+    /// Renders the component with the [`Csp`](crate::csp::Csp) sources of its inline blocks.
+    fn rendered(&self) -> Rendered {
+        let mut out = Buffer::new();
+        self.render_to(&mut out);
+        Rendered::from(out)
+    }
+}
+
+/// A [`Component`] rendered by a closure.
+pub struct FnComponent<F>(pub F);
+
+impl<F: Fn(&mut Buffer)> Component for FnComponent<F> {
+    fn render_to(&self, out: &mut Buffer) {
+        (self.0)(out)
+    }
+}
+
+impl Component for Origami {
+    fn render_to(&self, out: &mut Buffer) {
+        out.push_str(&self.0);
+    }
+}
+
+impl Component for Rendered {
+    fn render_to(&self, out: &mut Buffer) {
+        self.html.render_to(out);
+        for directive in [Directive::Script, Directive::Style] {
+            for source in self.csp.sources(directive) {
+                out.csp_source(directive, source.clone());
+            }
+        }
+    }
+}
+
+impl<T: Component + ?Sized> Component for &T {
+    fn render_to(&self, out: &mut Buffer) {
+        (**self).render_to(out)
+    }
+}
+
+impl<T: Component + ?Sized> Component for Box<T> {
+    fn render_to(&self, out: &mut Buffer) {
+        (**self).render_to(out)
+    }
+}
+
+impl<T: Component + ?Sized> Component for Rc<T> {
+    fn render_to(&self, out: &mut Buffer) {
+        (**self).render_to(out)
+    }
+}
+
+impl<T: Component + ?Sized> Component for Arc<T> {
+    fn render_to(&self, out: &mut Buffer) {
+        (**self).render_to(out)
+    }
+}
+
+// `@expr;` picks how to render the expression with the kind of `&&Wrap`: components through
+// `ComponentKind`, text through `TextKind`, and anything else through `StrKind`, as a `&str` the
+// expression is dereferenced to. Method resolution tries the impl on `&&Wrap` first, then the ones
+// on `&Wrap` and `Wrap`, so each kind is only picked when the previous ones don't apply.
+#[doc(hidden)]
+pub struct Wrap<'a, T: ?Sized>(pub &'a T);
+
+impl<T: ?Sized> Clone for Wrap<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for Wrap<'_, T> {}
+
+#[doc(hidden)]
+pub trait ComponentKind {
+    fn kind(self) -> ComponentTag;
+}
+
+impl<T: Component + ?Sized> ComponentKind for &&Wrap<'_, T> {
+    fn kind(self) -> ComponentTag {
+        ComponentTag
+    }
+}
+
+#[doc(hidden)]
+pub struct ComponentTag;
+
+impl ComponentTag {
+    pub fn render<O: Output + ?Sized, T: Component + ?Sized>(
+        self,
+        out: &mut O,
+        component: &T,
+        _escape: bool,
+    ) {
+        out.render_component(component)
+    }
+}
+
+#[doc(hidden)]
+pub trait TextKind {
+    fn kind(self) -> TextTag;
+}
+
+impl<T: AsRef<str> + ?Sized> TextKind for &Wrap<'_, T> {
+    fn kind(self) -> TextTag {
+        TextTag
+    }
+}
+
+#[doc(hidden)]
+pub struct TextTag;
+
+impl TextTag {
+    pub fn render<T: AsRef<str> + ?Sized>(self, out: &mut String, text: &T, escape: bool) {
+        StrTag.render(out, text.as_ref(), escape)
+    }
+}
+
+#[doc(hidden)]
+pub trait StrKind {
+    fn kind(self) -> StrTag;
+}
+
+impl<T: ?Sized> StrKind for Wrap<'_, T> {
+    fn kind(self) -> StrTag {
+        StrTag
+    }
+}
+
+#[doc(hidden)]
+pub struct StrTag;
+
+impl StrTag {
+    #[allow(unused_variables)]
+    pub fn render(self, out: &mut String, text: &str, escape: bool) {
+        #[cfg(feature = "html_escape")]
+        if escape {
+            html_escape::encode_text_to_string(text, out);
+            return;
+        }
+        out.push_str(text);
+    }
+}
+
+// Templates render into a `Buffer`, or into a plain `String` given to `anon!` with `string s`.
+// A `String` has nowhere to keep the sources of the render, so components are rendered into a
+// buffer of their own and sources are dropped. Directives needing the context, outlets or the
+// `once` keys of the render only compile with a `Buffer`, through `AsBuffer`.
+#[doc(hidden)]
+pub trait Output {
+    fn render_component<T: Component + ?Sized>(&mut self, component: &T);
+
+    fn csp_source(&mut self, directive: Directive, source: &'static str);
+}
+
+impl Output for Buffer {
+    fn render_component<T: Component + ?Sized>(&mut self, component: &T) {
+        component.render_to(self)
+    }
+
+    fn csp_source(&mut self, directive: Directive, source: &'static str) {
+        Buffer::csp_source(self, directive, source)
+    }
+}
+
+impl Output for String {
+    fn render_component<T: Component + ?Sized>(&mut self, component: &T) {
+        let mut out = Buffer::new();
+        component.render_to(&mut out);
+        self.push_str(&out.into_string());
+    }
+
+    fn csp_source(&mut self, _directive: Directive, _source: &'static str) {}
+}
+
+impl<O: Output + ?Sized> Output for &mut O {
+    fn render_component<T: Component + ?Sized>(&mut self, component: &T) {
+        (**self).render_component(component)
+    }
+
+    fn csp_source(&mut self, directive: Directive, source: &'static str) {
+        (**self).csp_source(directive, source)
+    }
+}
+
+#[doc(hidden)]
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a `Buffer`",
+    label = "rendered into a `String`",
+    note = "`#provide`, `use_context`, `#outlet`, `#portal`, `once` and `nonce` need the render \
+            to be a `Buffer`"
+)]
+pub trait AsBuffer {
+    fn as_buffer(&self) -> &Buffer;
+
+    fn as_buffer_mut(&mut self) -> &mut Buffer;
+}
+
+impl AsBuffer for Buffer {
+    fn as_buffer(&self) -> &Buffer {
+        self
+    }
+
+    fn as_buffer_mut(&mut self) -> &mut Buffer {
+        self
+    }
+}
+
+impl<O: AsBuffer + ?Sized> AsBuffer for &mut O {
+    fn as_buffer(&self) -> &Buffer {
+        (**self).as_buffer()
+    }
+
+    fn as_buffer_mut(&mut self) -> &mut Buffer {
+        (**self).as_buffer_mut()
+    }
+}
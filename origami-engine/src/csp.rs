@@ -0,0 +1,274 @@
+//! Content Security Policy sources of inline `script` and `style` blocks.
+//!
+//! The SHA-256 hash of every inline block is computed at compile time, after minification, and
+//! added to the [`Csp`] of the render when the block is rendered. `script nonce { .. }` and
+//! `style nonce { .. }` are allowed by the [`Nonce`] provided in the render context instead, or by
+//! their hash if no nonce is provided.
+//!
+//! With the `axum` feature, [`CspLayer`] adds the sources of pages built from
+//! [`Rendered`](crate::Rendered) to the `Content-Security-Policy` header of their responses.
+
+use std::borrow::Cow;
+use std::fmt;
+#[cfg(feature = "axum")]
+use std::future::Future;
+#[cfg(feature = "axum")]
+use std::pin::Pin;
+#[cfg(feature = "axum")]
+use std::sync::Arc;
+#[cfg(feature = "axum")]
+use std::task::{Context, Poll};
+
+#[cfg(feature = "axum")]
+use axum::extract::Request;
+#[cfg(feature = "axum")]
+use axum::http::header::CONTENT_SECURITY_POLICY;
+#[cfg(feature = "axum")]
+use axum::http::HeaderValue;
+#[cfg(feature = "axum")]
+use axum::response::Response;
+#[cfg(feature = "axum")]
+use tower_layer::Layer;
+#[cfg(feature = "axum")]
+use tower_service::Service;
+
+/// Directive a source is allowed by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Directive {
+    /// `script-src`
+    Script,
+    /// `style-src`
+    Style,
+}
+
+impl Directive {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Script => "script-src",
+            Self::Style => "style-src",
+        }
+    }
+}
+
+/// Sources of a page or a component, e.g. `'sha256-..'` and `'nonce-..'`.
+///
+/// Displays as the policy allowing them, e.g. `script-src 'sha256-..'; style-src 'nonce-..'`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Csp {
+    script_src: Vec<Cow<'static, str>>,
+    style_src: Vec<Cow<'static, str>>,
+}
+
+impl Csp {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `source` to `directive`, unless it is already there.
+    pub fn add(&mut self, directive: Directive, source: impl Into<Cow<'static, str>>) {
+        let source = source.into();
+        let sources = match directive {
+            Directive::Script => &mut self.script_src,
+            Directive::Style => &mut self.style_src,
+        };
+        if !sources.contains(&source) {
+            sources.push(source);
+        }
+    }
+
+    /// Sources of `directive`, in the order they were added.
+    pub fn sources(&self, directive: Directive) -> &[Cow<'static, str>] {
+        match directive {
+            Directive::Script => &self.script_src,
+            Directive::Style => &self.style_src,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.script_src.is_empty() && self.style_src.is_empty()
+    }
+
+    /// Adds the sources to `policy`, a policy of the application like `default-src 'self'`.
+    ///
+    /// Browsers enforce every policy of a response, so the sources have to be part of the
+    /// application's policy rather than sent in a policy of their own. They are only added where
+    /// the policy restricts inline blocks, i.e. to `script-src` and `style-src`, or to a copy of
+    /// `default-src`.
+    ///
+    /// Directives allowing `'unsafe-inline'` are left untouched: browsers ignore `'unsafe-inline'`
+    /// in a directive with a hash or a nonce, which would block the inline blocks and attributes
+    /// the application allows.
+    pub fn merge_into(&self, policy: &str) -> String {
+        let mut directives = policy
+            .split(';')
+            .map(str::trim)
+            .filter(|directive| !directive.is_empty())
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+        for directive in [Directive::Script, Directive::Style] {
+            let sources = self.sources(directive);
+            if sources.is_empty() {
+                continue;
+            }
+            let name = directive.name();
+            let position = match directives
+                .iter()
+                .position(|existing| directive_name(existing) == name)
+            {
+                Some(position) => position,
+                None => {
+                    let Some(default) = directives
+                        .iter()
+                        .find(|existing| directive_name(existing) == "default-src")
+                    else {
+                        continue;
+                    };
+                    if allows_unsafe_inline(default) {
+                        continue;
+                    }
+                    // The new directive replaces `default-src`, so it starts with its sources.
+                    directives.push(default.replacen("default-src", name, 1));
+                    directives.len() - 1
+                }
+            };
+            if allows_unsafe_inline(&directives[position]) {
+                continue;
+            }
+            for source in sources {
+                directives[position].push(' ');
+                directives[position].push_str(source);
+            }
+        }
+        directives.join("; ")
+    }
+}
+
+fn directive_name(directive: &str) -> &str {
+    directive
+        .split_ascii_whitespace()
+        .next()
+        .unwrap_or_default()
+}
+
+fn allows_unsafe_inline(directive: &str) -> bool {
+    directive
+        .split_ascii_whitespace()
+        .any(|source| source.eq_ignore_ascii_case("'unsafe-inline'"))
+}
+
+impl fmt::Display for Csp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut separator = "";
+        for directive in [Directive::Script, Directive::Style] {
+            let sources = self.sources(directive);
+            if !sources.is_empty() {
+                write!(f, "{}{} {}", separator, directive.name(), sources.join(" "))?;
+                separator = "; ";
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Nonce of a render, provided in the render context for `script nonce` and `style nonce`.
+///
+/// It must be generated for every response by a cryptographically secure random generator, e.g.
+/// 16 random bytes encoded in base64.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nonce(pub String);
+
+impl Nonce {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+}
+
+/// Layer setting the `Content-Security-Policy` header of responses to the policy of the
+/// application, with the sources of the page added for responses built from
+/// [`Rendered`](crate::Rendered).
+///
+/// Where the sources cannot be sent in a header, e.g. a [`Nonce`] with a line break, the policy of
+/// the application is sent without them.
+#[cfg(feature = "axum")]
+#[derive(Debug, Clone)]
+pub struct CspLayer {
+    policy: Arc<str>,
+    header: Option<HeaderValue>,
+}
+
+#[cfg(feature = "axum")]
+impl CspLayer {
+    /// Layer with the policy of the application, e.g. `default-src 'self'`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the policy is not a valid header value.
+    pub fn new(policy: impl Into<String>) -> Self {
+        let policy: Arc<str> = policy.into().into();
+        let header = (!policy.is_empty()).then(|| {
+            HeaderValue::from_str(&policy)
+                .expect("the Content-Security-Policy is a valid header value")
+        });
+        Self { policy, header }
+    }
+}
+
+#[cfg(feature = "axum")]
+impl<S> Layer<S> for CspLayer {
+    type Service = CspService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CspService {
+            inner,
+            policy: self.policy.clone(),
+            header: self.header.clone(),
+        }
+    }
+}
+
+/// Service of [`CspLayer`].
+#[cfg(feature = "axum")]
+#[derive(Debug, Clone)]
+pub struct CspService<S> {
+    inner: S,
+    policy: Arc<str>,
+    header: Option<HeaderValue>,
+}
+
+#[cfg(feature = "axum")]
+impl<S> Service<Request> for CspService<S>
+where
+    S: Service<Request, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let policy = self.policy.clone();
+        let header = self.header.clone();
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let mut response = response.await?;
+            let header = match response.extensions().get::<Csp>() {
+                Some(csp) => match csp.merge_into(&policy) {
+                    merged if merged.is_empty() => None,
+                    // Without the sources the page's blocks are blocked, never allowed.
+                    merged => HeaderValue::from_str(&merged).ok().or(header),
+                },
+                None => header,
+            };
+            if let Some(header) = header {
+                response
+                    .headers_mut()
+                    .insert(CONTENT_SECURITY_POLICY, header);
+            }
+            Ok(response)
+        })
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+use serde::Serialize;
+pub use serde_json::Map;
+use serde_json::Value;
+
+use crate::runtime::Template;
+use crate::Buffer;
+
+/// Variables of the template are passed to reloaded templates when they implement `Serialize`.
+/// Method resolution prefers the impl on `&&Data`, so the other impl is only picked when they
+/// don't, and the variable is then unknown to the reloaded template.
+pub struct Data<'a, T: ?Sized>(pub &'a T);
+
+pub trait SerializeData {
+    fn value(self) -> Option<Value>;
+}
+
+impl<T: Serialize + ?Sized> SerializeData for &&Data<'_, T> {
+    fn value(self) -> Option<Value> {
+        serde_json::to_value(self.0).ok()
+    }
+}
+
+pub trait OpaqueData {
+    fn value(self) -> Option<Value>;
+}
+
+impl<T: ?Sized> OpaqueData for &Data<'_, T> {
+    fn value(self) -> Option<Value> {
+        None
+    }
+}
+
+/// Templates parsed from files, with the modification time and length of the file they were
+/// parsed from. `None` for files that are the compiled template, or cannot be parsed.
+type Cache = HashMap<String, ((SystemTime, u64), Option<Arc<Template>>)>;
+
+static TEMPLATES: OnceLock<Mutex<Cache>> = OnceLock::new();
+
+/// Returns the template file at `path` if it differs from the `compiled` source. The file is only
+/// read again when its modification time or length changes.
+pub fn template(path: &str, compiled: &str) -> Option<Arc<Template>> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let stamp = (metadata.modified().ok()?, metadata.len());
+    let mut templates = TEMPLATES.get_or_init(Default::default).lock().unwrap();
+    if let Some((cached, template)) = templates.get(path) {
+        if *cached == stamp {
+            return template.clone();
+        }
+    }
+    let template = std::fs::read_to_string(path)
+        .ok()
+        .filter(|source| source != compiled)
+        .and_then(|source| match Template::parse(&source) {
+            Ok(template) => Some(Arc::new(template)),
+            Err(error) => {
+                log::warn!(
+                    "cannot reload `{}`, using the compiled template: {}",
+                    path,
+                    error
+                );
+                None
+            }
+        });
+    templates.insert(path.to_owned(), (stamp, template.clone()));
+    template
+}
+
+/// Renders a prop of a reloaded template, returning its markup.
+pub fn prop(out: &mut Buffer, render: impl FnOnce(&mut Buffer)) -> String {
+    let mut prop = out.fork();
+    render(&mut prop);
+    out.join_csp(prop.csp());
+    prop.into_string()
+}
+
+/// Renders a reloaded `template`, returning whether it succeeded. Otherwise the caller renders
+/// the compiled version.
+pub fn render(
+    out: &mut Buffer,
+    path: &str,
+    template: &Template,
+    slots: &[(&str, String)],
+    data: Map<String, Value>,
+) -> bool {
+    // Rendered into a fork, so a failing render leaves no partial output.
+    let mut hot = out.fork();
+    match template.render_with_slots(&mut hot, &Value::Object(data), slots) {
+        Ok(()) => {
+            out.join(hot);
+            true
+        }
+        Err(error) => {
+            log::warn!(
+                "cannot render `{}`, using the compiled template: {}",
+                path,
+                error
+            );
+            false
+        }
+    }
+}
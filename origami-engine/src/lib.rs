@@ -50,6 +50,23 @@
 //! );
 //! ```
 //!
+//! Props that are never used, and `@name;` that looks like a misspelled prop, are warned about.
+//! To render a variable from the enclosing scope whose name is close to a prop, wrap it in
+//! parentheses: `@(name);`.
+//!
+//! ```rust
+//! use origami_engine::comp;
+//!
+//! let titles = "Posts";
+//! comp! {
+//!     heading(title) =>
+//!     h1 { @title; " - " @(titles); }
+//! }
+//!
+//! let html = heading!(title { "Home" });
+//! assert_eq!(html.0, "<h1>Home - Posts</h1>");
+//! ```
+//!
 //! ## Layout
 //!
 //! You can create a layout structure that includes a navigation bar, a body for dynamic content, and a footer. Below is an example demonstrating this layout.
@@ -91,6 +108,7 @@
 //!
 //! let html = home!(cap => 250); // It is recommended to provide `cap`, i.e., the maximum length of html
 //!                              // to avoid unnecessary reallocations of strings
+//! # #[cfg(not(feature = "minify_document"))]
 //! assert_eq!(
 //!     html.0,
 //!     r#"<nav><ul><li><a>Home</a></li><li><a>About</a></li><li><a>Contact</a></li></ul></nav><main><h1>Welcome to the Homepage!</h1><p>This is the main content of the homepage.</p></main><footer><p>© 2024 Your Company</p></footer>"#
@@ -109,11 +127,802 @@
 //!
 //! let html = about!(cap => 250); // It is recommended to provide `cap`, i.e., the maximum length of html
 //!                              // to avoid unnecessary reallocations of strings
+//! # #[cfg(not(feature = "minify_document"))]
 //! assert_eq!(
 //!     html.0,
 //!     r#"<nav><ul><li><a>Home</a></li><li><a>About</a></li><li><a>Contact</a></li></ul></nav><main><h1>About Us</h1><p>We are committed to delivering quality service.</p></main><footer><p>© 2024 Your Company</p></footer>"#
 //! );
 //! ```
+//! ## Template Inheritance
+//!
+//! A base component can declare overridable regions with `block name { .. }`, the markup inside
+//! being the default. Another component extends it with `#extends base { block name { .. } }`,
+//! overriding any of its blocks in any order and inheriting the rest. Props of the base are
+//! passed as blocks too, and blocks can be declared inside overrides to build deeper hierarchies.
+//!
+//! ```rust
+//! use origami_engine::comp;
+//!
+//! comp! {
+//!     base(content) =>
+//!     html {
+//!         head {
+//!             title { block title { "Site" } }
+//!             block head {}
+//!         }
+//!         body {
+//!             aside { block sidebar { a href="/" { "Home" } } }
+//!             main { @content; }
+//!         }
+//!     }
+//! }
+//!
+//! comp! {
+//!     about =>
+//!     #extends base {
+//!         block title { "About" }
+//!         block content { p { "About us" } }
+//!     }
+//! }
+//!
+//! let html = about!();
+//! # #[cfg(not(feature = "minify_document"))]
+//! assert_eq!(
+//!     html.0,
+//!     r#"<html><head><title>About</title></head><body><aside><a href="/">Home</a></aside><main><p>About us</p></main></body></html>"#
+//! );
+//! ```
+//!
+//! Blocks are optional props of the base, after its declared props in the order they appear,
+//! so the base can also be called with `call` or directly.
+//!
+//! ## Local Templates
+//!
+//! Small fragments repeated within one component can be defined with
+//! `def name(params) { .. }` and called with `name(args)`. Calls are expanded inline, and the
+//! definition is only visible in the block it is defined in. Arguments are Rust expressions bound
+//! to the parameters by value.
+//!
+//! ```rust
+//! use origami_engine::comp;
+//!
+//! comp! {
+//!     tags =>
+//!     def badge(text) {
+//!         span class="badge" { @text; }
+//!     }
+//!     div {
+//!         badge("new")
+//!         for tag in ["rust", "html"]; {
+//!             badge(tag)
+//!         }
+//!     }
+//! }
+//!
+//! let html = tags!();
+//! # #[cfg(not(feature = "minify_document"))]
+//! assert_eq!(
+//!     html.0,
+//!     r#"<div><span class="badge">new</span><span class="badge">rust</span><span class="badge">html</span></div>"#
+//! );
+//! ```
+//!
+//! ## Optional Slots
+//!
+//! A prop declared with fallback markup, `prop = { .. }`, may be omitted by the caller. The
+//! fallback is rendered when the prop is omitted or passed empty. Use `has_slot(prop)` in a
+//! condition to check whether the caller passed anything, e.g. to skip wrapper elements.
+//!
+//! ```rust
+//! use origami_engine::comp;
+//!
+//! comp! {
+//!     layout(content, title = { "Untitled" }, footer = {}) =>
+//!     h1 { @title; }
+//!     main { @content; }
+//!     if has_slot(footer); {
+//!         footer { @footer; }
+//!     }
+//! }
+//!
+//! let html = layout!(content { "foo" });
+//! assert_eq!(html.0, "<h1>Untitled</h1><main>foo</main>");
+//!
+//! let html = layout!(content { "foo" }, footer { "bar" });
+//! assert_eq!(html.0, "<h1>Untitled</h1><main>foo</main><footer>bar</footer>");
+//! ```
+//!
+//! ## Scoped Slots
+//!
+//! A prop used with arguments, like `@row(item);`, becomes a scoped slot: the caller passes a
+//! template with matching parameters, `row |item| { .. }`, and the component renders it with the
+//! values it provides. This lets list, table and tree components leave item rendering to the caller.
+//!
+//! ```rust
+//! use origami_engine::comp;
+//!
+//! struct User {
+//!     name: &'static str,
+//!     email: &'static str,
+//! }
+//!
+//! comp! {
+//!     data_table(rows, row) =>
+//!     table {
+//!         for item in @rows;; {
+//!             tr { @row(item); }
+//!         }
+//!     }
+//! }
+//!
+//! let users = [User { name: "foo", email: "foo@example.com" }];
+//! let html = data_table!(
+//!     rows { &users },
+//!     row |user| {
+//!         td { @user.name; }
+//!         td { @user.email; }
+//!     }
+//! );
+//! # #[cfg(not(feature = "minify_document"))]
+//! assert_eq!(
+//!     html.0,
+//!     "<table><tr><td>foo</td><td>foo@example.com</td></tr></table>"
+//! );
+//! ```
+//!
+//! ## Generic Components
+//!
+//! Props can be given a type, and components can declare generic type and lifetime parameters
+//! with an optional `where` clause. Such a component is rendered by a generated generic function:
+//! typed props are passed as Rust expressions, and type errors point at the prop in the caller.
+//! Props without a type are still markup, rendered with `@prop;`.
+//!
+//! ```rust
+//! use std::fmt::Display;
+//!
+//! use origami_engine::comp;
+//!
+//! comp! {
+//!     select_input<'a, T>(name: &'a str, options: &'a [T], selected: &T, label) where T: Display + PartialEq =>
+//!     label { @label; }
+//!     select name=@name; {
+//!         for option in options; {
+//!             if option == selected; {
+//!                 option selected { @option.to_string().as_str(); }
+//!             } else {
+//!                 option { @option.to_string().as_str(); }
+//!             }
+//!         }
+//!     }
+//! }
+//!
+//! let html = select_input!(
+//!     name { "size" },
+//!     options { &[1, 2] },
+//!     selected { &2 },
+//!     label { "Size" }
+//! );
+//! assert_eq!(
+//!     html.0,
+//!     r#"<label>Size</label><select name="size"><option>1</option><option selected>2</option></select>"#
+//! );
+//! ```
+//!
+//! As the body is a function, it can only use its props and items in scope, not local variables
+//! of the caller.
+//!
+//! ## Recursive Components
+//!
+//! A component marked `#[recursive]` is rendered by a generated function, like a generic
+//! component, so it can call itself with `call` to render tree structures such as nested
+//! comments or menus. Untyped props of a recursive component are passed as
+//! `&dyn Component`.
+//!
+//! ```rust
+//! use origami_engine::comp;
+//!
+//! struct Item {
+//!     label: &'static str,
+//!     children: Vec<Item>,
+//! }
+//!
+//! comp! {
+//!     #[recursive]
+//!     tree_node(item: &Item) =>
+//!     li {
+//!         @item.label;
+//!         if !item.children.is_empty(); {
+//!             ul {
+//!                 for child in &item.children; {
+//!                     call tree_node { item { child } }
+//!                 }
+//!             }
+//!         }
+//!     }
+//! }
+//!
+//! let item = Item {
+//!     label: "root",
+//!     children: vec![Item { label: "leaf", children: vec![] }],
+//! };
+//! let html = tree_node!(item { &item });
+//! assert_eq!(html.0, "<li>root<ul><li>leaf</li></ul></li>");
+//! ```
+//!
+//! ## Context
+//!
+//! Values needed deep in the component tree, like the current user or locale, don't have to be
+//! passed through every component. `#provide expr; { .. }` makes a value available to everything
+//! rendered inside the block, and `use_context::<T>()` returns the innermost provided value of type
+//! `T` as an `Option<Arc<T>>`. Context values live in the [`Buffer`] of the current render.
+//!
+//! ```rust
+//! use origami_engine::comp;
+//!
+//! struct Locale(&'static str);
+//!
+//! comp! {
+//!     greeting =>
+//!     p {
+//!         if use_context::<Locale>().is_some_and(|locale| locale.0 == "de"); {
+//!             "Hallo!"
+//!         } else {
+//!             "Hello!"
+//!         }
+//!     }
+//! }
+//!
+//! comp! {
+//!     sidebar =>
+//!     aside { call greeting {} }
+//! }
+//!
+//! comp! {
+//!     layout =>
+//!     #provide Locale("de"); {
+//!         call sidebar {}
+//!     }
+//!     call sidebar {}
+//! }
+//!
+//! let html = layout!();
+//! assert_eq!(html.0, "<aside><p>Hallo!</p></aside><aside><p>Hello!</p></aside>");
+//! ```
+//!
+//! ## Portals
+//!
+//! A layout can declare named outlets with `#outlet name;`, and `#portal name { .. }` anywhere
+//! below renders its content into that outlet instead of in place, e.g. `<title>` into `<head>`
+//! or modals at the end of `<body>`. Portal content is collected in the [`Buffer`] and inserted at
+//! the outlets when the render finishes, so outlets may come before or after their portals.
+//! Content for an outlet that is never declared is discarded. Like `#provide`, `#include` and
+//! `#extends`, these directives start with `#`, so elements of the same name, e.g. `<portal>`, are
+//! still written as usual.
+//!
+//! ```rust
+//! use origami_engine::comp;
+//!
+//! comp! {
+//!     layout(content) =>
+//!     html {
+//!         head { #outlet head; }
+//!         body { @content; }
+//!     }
+//! }
+//!
+//! comp! {
+//!     page_title(title) =>
+//!     #portal head { title { @title; } }
+//!     h1 { @title; }
+//! }
+//!
+//! comp! {
+//!     home =>
+//!     call layout { content { call page_title { title { "Home" } } } }
+//! }
+//!
+//! let html = home!();
+//! assert_eq!(
+//!     html.0,
+//!     "<html><head><title>Home</title></head><body><h1>Home</h1></body></html>"
+//! );
+//! ```
+//!
+//! ## Once Blocks
+//!
+//! A component with a `style` or `script` block emits it every time it is called.
+//! With `style once` or `script once`, the block is emitted only the first time it is rendered
+//! in a render, keyed by a compile-time hash of the block after minification. `once(name)` also
+//! hoists the block into the outlet `name`, see [Portals](#portals).
+//!
+//! ```rust
+//! use origami_engine::comp;
+//!
+//! comp! {
+//!     badge(text) =>
+//!     style once(head) { ".badge { color: red; }" }
+//!     span class="badge" { @text; }
+//! }
+//!
+//! comp! {
+//!     page =>
+//!     html {
+//!         head { #outlet head; }
+//!         body {
+//!             for text in ["a", "b"]; {
+//!                 call badge { text { @text; } }
+//!             }
+//!         }
+//!     }
+//! }
+//!
+//! let html = page!();
+//! # #[cfg(not(feature = "minify_document"))]
+//! assert_eq!(
+//!     html.0,
+//!     r#"<html><head><style>.badge { color: red; }</style></head><body><span class="badge">a</span><span class="badge">b</span></body></html>"#
+//! );
+//! ```
+//!
+//! ## Scoped Styles
+//!
+//! Selectors in a `style scoped` block only match elements of the component that declares it.
+//! The component gets a `data-o-<hash>` attribute computed at compile time, added to every element
+//! it renders and to the last compound selector of every rule, e.g. `.btn:hover` becomes
+//! `.btn[data-o-3fa2c1d0]:hover`. The hash is derived from the SHA-256 of the component's name
+//! and body, so it only changes with them. Markup passed in by the caller is not scoped.
+//!
+//! ```rust
+//! use origami_engine::comp;
+//!
+//! comp! {
+//!     button_component(label) =>
+//!     style scoped { ".btn { color: red; }" }
+//!     button class="btn" { @label; }
+//! }
+//!
+//! let html = button_component!(label { span { "Save" } });
+//! let start = html.0.find("data-o-").unwrap();
+//! let scope = &html.0[start..start + 15];
+//! # #[cfg(not(feature = "minify_document"))]
+//! assert_eq!(
+//!     html.0,
+//!     format!(r#"<style>.btn[{scope}] {{ color: red; }}</style><button class="btn" {scope}><span>Save</span></button>"#)
+//! );
+//! ```
+//!
+//! ## Including Files
+//!
+//! Instead of inline content, `style` and `script` blocks can read a file given with
+//! `src_file="path"`, and `#include "path";` inserts a file of raw HTML. Paths are relative to
+//! `CARGO_MANIFEST_DIR`, files are read and minified at compile time like inline content, and
+//! changes to them trigger recompilation. Use `nominify` to keep the content as it is, e.g.
+//! `#include nominify "path";`.
+//!
+//! ```rust
+//! use origami_engine::comp;
+//!
+//! comp! {
+//!     page =>
+//!     style src_file="tests/assets/card.css";
+//!     div class="card" { "Card" }
+//!     #include "tests/assets/footer.html";
+//! }
+//!
+//! let html = page!();
+//! # #[cfg(all(feature = "minify_html", not(feature = "minify_document")))]
+//! assert_eq!(
+//!     html.0,
+//!     "<style>.card { color: red; }</style><div class=\"card\">Card</div><footer><p>Footer</footer>"
+//! );
+//! ```
+//!
+//! ## Minifier Options
+//!
+//! `script`, `style` and `#include` are minified with the default options of
+//! [`minify_html::Cfg`]. A crate sets its own options in `origami.toml` next to its
+//! `Cargo.toml`, and a block overrides them with `minify(..)`, an option without a value being
+//! set to `true`. Changes to `origami.toml` trigger recompilation. Runtime templates only apply
+//! the options of blocks.
+//!
+//! ```toml
+//! [minify]
+//! keep_comments = true
+//! minify_js = false
+//! ```
+//!
+//! ```rust
+//! # #[cfg(feature = "minify_html")]
+//! # {
+//! use origami_engine::comp;
+//!
+//! comp! {
+//!     page =>
+//!     #include minify(keep_comments) "tests/assets/comments.html";
+//!     script minify(minify_js = false) { "console.log(1);" }
+//! }
+//!
+//! let html = page!();
+//! assert_eq!(
+//!     html.0,
+//!     "<!--[if IE]><p>Old browser</p><![endif]--><p>Hello<script>console.log(1);</script>"
+//! );
+//! # }
+//! ```
+//!
+//! The CSS of `style` blocks and the JavaScript of `script` blocks are parsed at compile time,
+//! minified or not, so a syntax error fails the build at its line and column in the source or
+//! `src_file` instead of being sent as broken output. Scripts of other types, e.g.
+//! `type="text/template"`, are not checked.
+//!
+//! ## Document Minification
+//!
+//! With the `minify_document` feature, the static markup of templates is minified at compile
+//! time too: whitespace in text is collapsed outside `pre` and `textarea`, attribute values are
+//! unquoted where it is safe, the contents of `style` and `on*` attributes are minified, and
+//! optional closing tags of `li`, `p`, `td` and similar elements are dropped when the next
+//! sibling is an element written in the template, e.g. a `li` before another `li`. The output
+//! of expressions is never changed.
+//!
+//! Collapsing whitespace assumes it is not rendered, so keep the feature off for templates
+//! styled with `white-space: pre`.
+//!
+//! ```rust
+//! #[cfg(feature = "minify_document")]
+//! {
+//!     use origami_engine::comp;
+//!
+//!     let name = "Jane  Doe";
+//!     comp! {
+//!         card =>
+//!         ul class="people" style="margin: 0px" {
+//!             li {
+//!                 "Name:
+//!                 " @name;
+//!             }
+//!             li { "Age: 42" }
+//!         }
+//!     }
+//!
+//!     let html = card!();
+//!     assert_eq!(
+//!         html.0,
+//!         "<ul class=people style=margin:0><li>Name: Jane  Doe<li>Age: 42</li></ul>"
+//!     );
+//! }
+//! ```
+//!
+//! ## Crate Configuration
+//!
+//! `origami.toml` also sets the defaults of the crate: `[defaults]` has `escape`, `minify` for
+//! `script`, `style` and `#include` blocks, `minify_document`, and `self_closing` (`"slash"` for
+//! `<br/>`, `"html"` for `<br>`), and `[lints]` sets the `unused_props` and `similar_props`
+//! checks to `"allow"`, `"warn"` or `"deny"`. The defaults are all on, and both checks warn by
+//! default. Errors in the file fail the build as `path:line:column` with the key at fault.
+//!
+//! ```toml
+//! [defaults]
+//! self_closing = "html"
+//!
+//! [lints]
+//! unused_props = "deny"
+//! ```
+//!
+//! A component overrides them with `#[origami(..)]`, where `minify(..)` replaces the options
+//! of the `[minify]` table. `escape` applies where the component is rendered on its own,
+//! components called from a template escape like their caller. `#[macro_export]` components
+//! keep the configuration of the crate declaring them when other crates call them.
+//!
+//! ```rust
+//! use origami_engine::comp;
+//!
+//! comp! {
+//!     #[origami(self_closing = "html")]
+//!     field(label, hint) =>
+//!     label { @label; input name="email"; }
+//! }
+//!
+//! let html = field!(label { "Email" }, hint {});
+//! # #[cfg(not(feature = "minify_document"))]
+//! assert_eq!(html.0, "<label>Email<input name=\"email\"></label>");
+//! ```
+//!
+//! ## Template Files
+//!
+//! [`comp_file!`] declares a component like [`comp!`], with the body read from a file relative
+//! to `CARGO_MANIFEST_DIR`. The file holds the same syntax as the body of [`comp!`], syntax
+//! errors are reported at the path with the message of the parser, and changes to the file
+//! trigger recompilation.
+//!
+//! ```text
+//! html {
+//!     head {
+//!         title { @title; }
+//!     }
+//!     body {
+//!         h1 { @title; }
+//!         @body;
+//!         for item in items.iter(); {
+//!             p { @item; }
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! ```rust
+//! use origami_engine::comp_file;
+//!
+//! let items = ["One", "Two"];
+//! comp_file!(page(title, body) => "tests/assets/page.origami");
+//!
+//! let html = page!(title { "Home" } body { div { "Content" } });
+//! assert_eq!(
+//!     html.0,
+//!     "<html><head><title>Home</title></head><body><h1>Home</h1><div>Content</div><p>One</p><p>Two</p></body></html>"
+//! );
+//! ```
+//!
+//! ## Runtime Templates
+//!
+//! With the `runtime` feature, [`runtime::Template`] parses the same syntax from a string at
+//! runtime and renders it with any serializable data, e.g. for pages edited in a CMS. Fields of
+//! the data are the variables of the template. Expressions can only read the data: literals,
+//! variables, fields, indexing, operators, ranges and a few methods like `len` or `is_empty`.
+//! Escaping works as in [`comp!`], and components, `#provide` and files are not available.
+//! [`runtime::Templates`] keeps parsed templates by name, to be shared between requests.
+//!
+//! ```rust
+//! #[cfg(all(feature = "runtime", feature = "html_escape"))]
+//! {
+//!     use origami_engine::runtime::Templates;
+//!
+//!     let templates = Templates::new();
+//!     templates
+//!         .insert(
+//!             "posts",
+//!             r#"
+//!             h1 { @title; }
+//!             for post in posts; {
+//!                 if post.draft; {} else { p { @post.name; } }
+//!             }
+//!             "#,
+//!         )
+//!         .unwrap();
+//!
+//!     let data = serde_json::json!({
+//!         "title": "Fish & Chips",
+//!         "posts": [{ "name": "Cod", "draft": false }, { "name": "Hake", "draft": true }],
+//!     });
+//!     let html = templates.render("posts", &data).unwrap();
+//!     assert_eq!(html.0, "<h1>Fish &amp; Chips</h1><p>Cod</p>");
+//! }
+//! ```
+//!
+//! ## Hot Reload
+//!
+//! With the `hot_reload` feature, components declared with [`comp_file!`] check their template
+//! file on each render in debug builds, and render it with the runtime templates when it differs
+//! from the compiled one, so changes show up without recompiling. The file is only read again
+//! when its modification time or length changes. Props are rendered by the compiled code and
+//! inserted where the template renders them, and variables of the enclosing scope are passed to
+//! the template if they implement `Serialize`.
+//!
+//! Templates the runtime cannot parse or render, e.g. with method calls it does not support,
+//! fall back to the compiled version with a warning logged through the
+//! [`log`](https://docs.rs/log) crate, as do components with generics,
+//! typed props or scoped slots. Release builds always render the compiled version.
+//!
+//! ```toml
+//! [features]
+//! dev = ["origami-engine/hot_reload"]
+//! ```
+//!
+//! ## Live Reload
+//!
+//! With the `live_reload` feature, [`live_reload::LiveReloadLayer`] reloads pages in the browser
+//! when watched files change. It injects a small script into responses built from [`Origami`],
+//! allowed by its hash in the `Content-Security-Policy`, and serves the server-sent events
+//! endpoint the script listens to. Together with hot reload, template changes show up without
+//! recompiling or refreshing.
+//!
+//! ```rust
+//! #[cfg(feature = "live_reload")]
+//! {
+//!     use axum::routing::get;
+//!     use axum::Router;
+//!     use origami_engine::comp;
+//!     use origami_engine::live_reload::LiveReloadLayer;
+//!
+//!     comp! {
+//!         home =>
+//!         html { body { "Home" } }
+//!     }
+//!
+//!     let app: Router = Router::new().route("/", get(|| async { home!() }));
+//!     #[cfg(debug_assertions)]
+//!     let app = app.layer(LiveReloadLayer::new().watch("templates").watch("assets"));
+//! }
+//! ```
+//!
+//! ## Dynamic Components
+//!
+//! Types implementing [`Component`] can be stored as trait objects, e.g. widgets built from a
+//! configuration at runtime, and rendered with `@widget;`. A component defined with `comp!` is
+//! turned into a [`Component`] with `name!(dyn, ..props)`.
+//!
+//! ```rust
+//! use origami_engine::{comp, Buffer, Component};
+//!
+//! struct Divider;
+//!
+//! impl Component for Divider {
+//!     fn render_to(&self, out: &mut Buffer) {
+//!         out.push_str("<hr/>");
+//!     }
+//! }
+//!
+//! comp! {
+//!     heading(text) =>
+//!     h1 { @text; }
+//! }
+//!
+//! let title = String::from("Welcome");
+//! let widgets: Vec<Box<dyn Component>> = vec![
+//!     Box::new(heading!(dyn, text { @title.as_str(); })),
+//!     Box::new(Divider),
+//! ];
+//!
+//! comp! {
+//!     page =>
+//!     main {
+//!         for widget in &widgets; {
+//!             @widget;
+//!         }
+//!     }
+//! }
+//!
+//! let html = page!();
+//! assert_eq!(html.0, "<main><h1>Welcome</h1><hr/></main>");
+//! ```
+//!
+//! ## Sharing Components Across Crates
+//!
+//! Components are macros, so by default they follow `macro_rules!` scoping and can only be used
+//! inside the crate that defines them. Mark a `pub` component with `#[macro_export]` to make it
+//! usable from other crates, e.g. a shared design-system crate:
+//!
+//! ```rust
+//! // In the `ui` crate
+//! pub mod widgets {
+//!     use origami_engine::comp;
+//!
+//!     comp! {
+//!         /// A small label.
+//!         #[macro_export]
+//!         pub badge(text) =>
+//!         span class="badge" { @text; }
+//!     }
+//!
+//!     comp! {
+//!         #[macro_export]
+//!         pub card(title) =>
+//!         div class="card" {
+//!             // Paths starting with `crate` resolve to the defining crate,
+//!             // even when `card` is called from another crate
+//!             call crate::widgets::badge { text { "new" } }
+//!             h2 { @title; }
+//!         }
+//!     }
+//! }
+//!
+//! // In a crate depending on `ui`, this would be `call ui::widgets::card { .. }`
+//! # fn main() {
+//! use origami_engine::comp;
+//!
+//! comp! {
+//!     page =>
+//!     main {
+//!         call widgets::card { title { "Hello" } }
+//!     }
+//! }
+//!
+//! let html = page!();
+//! # #[cfg(not(feature = "minify_document"))]
+//! assert_eq!(
+//!     html.0,
+//!     r#"<main><div class="card"><span class="badge">new</span><h2>Hello</h2></div></main>"#
+//! );
+//! # }
+//! ```
+//!
+//! Exported components are called through their module path, just like any other item, and the
+//! consuming crate must depend on `origami-engine` as well. Inside an exported component, refer to
+//! other components and items of the same crate with `crate::` paths, as plain names are resolved
+//! where the component is called.
+//!
+//! ## Pretty Printing
+//!
+//! [`Origami::pretty`] puts block-level elements on their own lines, indented, e.g. for debugging
+//! or snapshot tests. Whitespace is only added where browsers don't render it, between
+//! block-level elements with no text or inline elements next to them. Elements containing text
+//! or inline elements, and the content of `pre`, `textarea`, `script` and `style`, are kept as
+//! they are.
+//!
+//! ```rust
+//! use origami_engine::comp;
+//!
+//! comp! {
+//!     list =>
+//!     ul {
+//!         li { "One" }
+//!         li { "Two " b { "bold" } }
+//!     }
+//!     pre { "  keep\n  this" }
+//! }
+//!
+//! let html = list!();
+//! # #[cfg(not(feature = "minify_document"))]
+//! assert_eq!(
+//!     html.pretty().0,
+//!     "<ul>\n  <li>One</li>\n  <li>Two <b>bold</b></li>\n</ul>\n<pre>  keep\n  this</pre>"
+//! );
+//! ```
+//!
+//! ## Content Security Policy
+//!
+//! The SHA-256 hashes of inline `script` and `style` blocks are computed at compile time, after
+//! minification, and collected in the [`csp::Csp`] of the render as the blocks are rendered.
+//! [`Component::rendered`] gives the markup with these sources, e.g. `name!(dyn).rendered()`, and
+//! `name!(csp)` gives the hashes of a component's own blocks. `script nonce`
+//! and `style nonce` render the [`csp::Nonce`] provided in the context instead, and add it to the
+//! sources rather than their hash. Without a nonce in the context, they are allowed by their hash.
+//!
+//! ```rust
+//! use origami_engine::csp::{Directive, Nonce};
+//! use origami_engine::{comp, Component};
+//!
+//! comp! {
+//!     page =>
+//!     style { "p{color:red}" }
+//!     #provide Nonce::new("r4nd0m"); {
+//!         script nonce { "console.log(1)" }
+//!     }
+//! }
+//!
+//! let page = page!(dyn).rendered();
+//! assert_eq!(
+//!     page.html.0,
+//!     "<style>p{color:red}</style><script nonce=\"r4nd0m\">console.log(1)</script>"
+//! );
+//! assert_eq!(
+//!     page.csp.sources(Directive::Script),
+//!     ["'nonce-r4nd0m'"]
+//! );
+//! ```
+//!
+//! With the `axum` feature, [`csp::CspLayer`] sets the `Content-Security-Policy` header to the
+//! policy of the application, with the sources of [`Rendered`] pages added. Runtime and hot reloaded
+//! templates render nonces but don't record hashes, and files of `#include` are not hashed.
+//!
+//! ```rust
+//! #[cfg(feature = "axum")]
+//! {
+//!     use axum::routing::get;
+//!     use axum::Router;
+//!     use origami_engine::csp::CspLayer;
+//!     use origami_engine::{comp, Component};
+//!
+//!     comp! {
+//!         home =>
+//!         html { body { script { "console.log(1)" } } }
+//!     }
+//!
+//!     let app: Router = Router::new()
+//!         .route("/", get(|| async { home!(dyn).rendered() }))
+//!         .layer(CspLayer::new("default-src 'self'"));
+//! }
+//! ```
+//!
 //! ## Escape and Noescape
 //!
 //! You can use `escape` and `noescape` to control HTML escaping behavior in the template (`html_escape` is feature is required):
@@ -228,12 +1037,71 @@
 //! }
 //! ```
 
+#[doc(hidden)]
+pub use origami_macros::__extends;
 pub use origami_macros::anon;
 pub use origami_macros::comp;
+pub use origami_macros::comp_file;
+
+mod buffer;
+mod component;
+pub mod csp;
+#[cfg(feature = "hot_reload")]
+mod hot_reload;
+#[cfg(feature = "live_reload")]
+pub mod live_reload;
+mod pretty;
+#[cfg(feature = "runtime")]
+pub mod runtime;
+
+pub use self::buffer::Buffer;
+pub use self::component::{Component, FnComponent};
 
 #[derive(Debug, Clone)]
 pub struct Origami(pub String);
 
+/// Rendered markup with the [`Csp`](csp::Csp) sources of its inline blocks, given by
+/// [`Component::rendered`].
+#[derive(Debug, Clone)]
+pub struct Rendered {
+    pub html: Origami,
+    pub csp: csp::Csp,
+}
+
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::component::{
+        AsBuffer, ComponentKind, ComponentTag, Output, StrKind, StrTag, TextKind, TextTag, Wrap,
+    };
+    #[cfg(feature = "hot_reload")]
+    pub use crate::hot_reload::{
+        prop as hot_prop, render as hot_render, template as hot_template, Data, Map, OpaqueData,
+        SerializeData,
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __prop_expr {
+    (@ $expr:expr ;) => {
+        $expr
+    };
+    ($expr:expr) => {
+        $expr
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __has_slot {
+    () => {
+        false
+    };
+    ($($tt:tt)+) => {
+        true
+    };
+}
+
 #[cfg(feature = "html_escape")]
 #[doc(no_inline)]
 pub use html_escape::encode_text_to_string;
@@ -247,6 +1115,19 @@ use ::axum::response::{Html, IntoResponse, Response};
 #[cfg(feature = "axum")]
 impl IntoResponse for Origami {
     fn into_response(self) -> Response {
-        Html(self.0).into_response()
+        #[allow(unused_mut)]
+        let mut response = Html(self.0).into_response();
+        #[cfg(feature = "live_reload")]
+        response.extensions_mut().insert(live_reload::Inject);
+        response
+    }
+}
+
+#[cfg(feature = "axum")]
+impl IntoResponse for Rendered {
+    fn into_response(self) -> Response {
+        let mut response = self.html.into_response();
+        response.extensions_mut().insert(self.csp);
+        response
     }
 }
@@ -0,0 +1,265 @@
+//! Reloading of pages in the browser when files change, for development servers.
+//!
+//! [`LiveReloadLayer`] watches files and directories, injects a script into responses built from
+//! [`Origami`](crate::Origami) or [`Rendered`](crate::Rendered), and serves the endpoint the
+//! script listens to with server-sent events. The page is reloaded whenever a watched file is created, changed or removed.
+
+use std::collections::hash_map::DefaultHasher;
+use std::convert::Infallible;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock, Weak};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::header::{CONTENT_LENGTH, CONTENT_SECURITY_POLICY};
+use axum::http::HeaderValue;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use sha2::{Digest, Sha256};
+use tokio::sync::watch;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::csp::{Csp, Directive};
+
+/// Marks responses built from [`Origami`](crate::Origami) or [`Rendered`](crate::Rendered),
+/// which get the reload script.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Inject;
+
+/// Layer reloading pages when watched files change.
+#[derive(Debug, Clone)]
+pub struct LiveReloadLayer {
+    shared: Arc<Shared>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    paths: Vec<PathBuf>,
+    endpoint: String,
+    interval: Duration,
+    sender: watch::Sender<u64>,
+    watcher: OnceLock<()>,
+}
+
+impl Default for LiveReloadLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LiveReloadLayer {
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                paths: Vec::new(),
+                endpoint: "/__origami/live-reload".to_owned(),
+                interval: Duration::from_millis(500),
+                sender: watch::channel(0).0,
+                watcher: OnceLock::new(),
+            }),
+        }
+    }
+
+    /// Watches a file, or every file in a directory and its subdirectories.
+    pub fn watch(mut self, path: impl Into<PathBuf>) -> Self {
+        self.shared_mut().paths.push(path.into());
+        self
+    }
+
+    /// Path of the event stream endpoint, `/__origami/live-reload` by default.
+    pub fn endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.shared_mut().endpoint = endpoint.into();
+        self
+    }
+
+    /// How often watched files are checked, every 500 milliseconds by default.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.shared_mut().interval = interval;
+        self
+    }
+
+    fn shared_mut(&mut self) -> &mut Shared {
+        Arc::get_mut(&mut self.shared).expect("LiveReloadLayer is configured before it is used")
+    }
+
+    /// Starts the thread checking the watched files, which stops once the layer is dropped.
+    fn start_watcher(&self) {
+        self.shared.watcher.get_or_init(|| {
+            // The files are checked once before returning, so later changes are never missed.
+            let state = self.shared.state();
+            let shared = Arc::downgrade(&self.shared);
+            let interval = self.shared.interval;
+            std::thread::spawn(move || watch_files(shared, state, interval));
+        });
+    }
+}
+
+impl<S> Layer<S> for LiveReloadLayer {
+    type Service = LiveReload<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        self.start_watcher();
+        let (script, source) = script(&self.shared.endpoint);
+        LiveReload {
+            inner,
+            shared: self.shared.clone(),
+            script: script.into(),
+            source: source.into(),
+        }
+    }
+}
+
+/// Service of [`LiveReloadLayer`].
+#[derive(Debug, Clone)]
+pub struct LiveReload<S> {
+    inner: S,
+    shared: Arc<Shared>,
+    script: Arc<str>,
+    /// Hash of the script, allowing it in the `Content-Security-Policy`.
+    source: Arc<str>,
+}
+
+impl<S> Service<Request> for LiveReload<S>
+where
+    S: Service<Request, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        if request.uri().path() == self.shared.endpoint {
+            let events = events(self.shared.sender.subscribe());
+            return Box::pin(async move { Ok(events.into_response()) });
+        }
+        let script = self.script.clone();
+        let source = self.source.clone();
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let response = response.await?;
+            if response.extensions().get::<Inject>().is_none() {
+                return Ok(response);
+            }
+            let (mut parts, body) = response.into_parts();
+            // The script is allowed by its hash, whether the policy is set before or after.
+            let mut csp = Csp::new();
+            csp.add(Directive::Script, source.to_string());
+            if let Some(page) = parts.extensions.get_mut::<Csp>() {
+                page.add(Directive::Script, source.to_string());
+            }
+            let policy = parts
+                .headers
+                .get(CONTENT_SECURITY_POLICY)
+                .and_then(|policy| policy.to_str().ok())
+                .map(|policy| csp.merge_into(policy));
+            if let Some(Ok(policy)) = policy.map(HeaderValue::try_from) {
+                parts.headers.insert(CONTENT_SECURITY_POLICY, policy);
+            }
+            let body = match axum::body::to_bytes(body, usize::MAX).await {
+                Ok(bytes) => Body::from(inject(&String::from_utf8_lossy(&bytes), &script)),
+                Err(_) => Body::empty(),
+            };
+            parts.headers.remove(CONTENT_LENGTH);
+            Ok(Response::from_parts(parts, body))
+        })
+    }
+}
+
+/// Sends a `reload` event on each change after the connection.
+fn events(
+    mut receiver: watch::Receiver<u64>,
+) -> Sse<impl futures_util::Stream<Item = Result<Event, Infallible>>> {
+    receiver.borrow_and_update();
+    let stream = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        receiver.changed().await.ok()?;
+        Some((
+            Ok(Event::default().event("reload").data("reload")),
+            receiver,
+        ))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Reload script listening to `endpoint`, and its `'sha256-..'` source.
+fn script(endpoint: &str) -> (String, String) {
+    let content = format!(
+        "new EventSource({:?}).addEventListener(\"reload\",()=>location.reload())",
+        endpoint
+    );
+    let source = format!(
+        "'sha256-{}'",
+        STANDARD.encode(Sha256::digest(content.as_bytes()))
+    );
+    (format!("<script>{}</script>", content), source)
+}
+
+/// Inserts `script` before the closing `body` tag, or at the end without one.
+fn inject(html: &str, script: &str) -> String {
+    let at = html.rfind("</body>").unwrap_or(html.len());
+    let mut injected = String::with_capacity(html.len() + script.len());
+    injected.push_str(&html[..at]);
+    injected.push_str(script);
+    injected.push_str(&html[at..]);
+    injected
+}
+
+impl Shared {
+    /// Hash of the watched files, changing when any of them changes.
+    fn state(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for path in &self.paths {
+            hash_files(path, &mut hasher);
+        }
+        hasher.finish()
+    }
+}
+
+fn watch_files(shared: Weak<Shared>, mut last: u64, interval: Duration) {
+    loop {
+        std::thread::sleep(interval);
+        let Some(shared) = shared.upgrade() else {
+            return;
+        };
+        let state = shared.state();
+        if state != last {
+            shared.sender.send_modify(|version| *version += 1);
+            last = state;
+        }
+    }
+}
+
+/// Hashes the paths, sizes and modification times of the files at `path`.
+fn hash_files(path: &Path, hasher: &mut DefaultHasher) {
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return;
+    };
+    if metadata.is_dir() {
+        let Ok(entries) = std::fs::read_dir(path) else {
+            return;
+        };
+        let mut entries = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect::<Vec<_>>();
+        entries.sort();
+        for entry in entries {
+            hash_files(&entry, hasher);
+        }
+        return;
+    }
+    path.hash(hasher);
+    metadata.len().hash(hasher);
+    metadata.modified().ok().hash(hasher);
+}
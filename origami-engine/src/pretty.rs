@@ -0,0 +1,271 @@
+use std::ops::Range;
+
+use crate::Origami;
+
+/// Elements whose surrounding whitespace is not rendered when their siblings are block-level too.
+const BLOCK: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "base",
+    "blockquote",
+    "body",
+    "caption",
+    "colgroup",
+    "dd",
+    "details",
+    "dialog",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "hgroup",
+    "hr",
+    "html",
+    "legend",
+    "li",
+    "link",
+    "main",
+    "menu",
+    "meta",
+    "nav",
+    "ol",
+    "optgroup",
+    "option",
+    "p",
+    "pre",
+    "script",
+    "section",
+    "style",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "template",
+    "tfoot",
+    "th",
+    "thead",
+    "title",
+    "tr",
+    "ul",
+];
+
+const VOID: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Elements whose content is kept as it is.
+const RAW: &[&str] = &["pre", "script", "style", "textarea"];
+
+enum Node {
+    Element(Element),
+    Text,
+    /// Comments and doctypes.
+    Other(Range<usize>),
+}
+
+struct Element {
+    name: String,
+    open: Range<usize>,
+    children: Vec<Node>,
+    /// Range of the element and its content, ending after the closing tag if there is one.
+    span: Range<usize>,
+    closed: bool,
+}
+
+impl Origami {
+    /// Returns the markup with block-level elements on their own lines, indented by two spaces.
+    ///
+    /// Whitespace is only added around block-level elements whose siblings are all block-level,
+    /// where browsers don't render it, so elements with text or inline elements, and the content
+    /// of `pre`, `textarea`, `script` and `style`, are kept as they are.
+    pub fn pretty(&self) -> Origami {
+        let html = &self.0;
+        let nodes = parse(html);
+        if !is_block_only(&nodes) {
+            return self.clone();
+        }
+        let mut out = String::with_capacity(html.len() * 2);
+        write_lines(&mut out, html, &nodes, 0);
+        Origami(out)
+    }
+}
+
+fn parse(html: &str) -> Vec<Node> {
+    let mut roots = Vec::new();
+    let mut stack: Vec<Element> = Vec::new();
+    let mut i = 0;
+    while i < html.len() {
+        let rest = &html[i..];
+        let next = rest.as_bytes().get(1).copied().unwrap_or(0);
+        if rest.starts_with("<!--") {
+            let end = rest.find("-->").map_or(html.len(), |end| i + end + 3);
+            push(&mut stack, &mut roots, Node::Other(i..end));
+            i = end;
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            let end = rest.find('>').map_or(html.len(), |end| i + end + 1);
+            push(&mut stack, &mut roots, Node::Other(i..end));
+            i = end;
+        } else if rest.starts_with("</")
+            && rest.as_bytes().get(2).is_some_and(u8::is_ascii_alphabetic)
+        {
+            let end = rest.find('>').map_or(html.len(), |end| i + end + 1);
+            let name = tag_name(&html[i + 2..end]);
+            match stack.iter().rposition(|element| element.name == name) {
+                Some(pos) => {
+                    while stack.len() > pos + 1 {
+                        close(&mut stack, &mut roots, html.len(), false);
+                    }
+                    close(&mut stack, &mut roots, end, true);
+                }
+                // A stray closing tag creates an element, so it is kept as it is, like text.
+                None => push(&mut stack, &mut roots, Node::Text),
+            }
+            i = end;
+        } else if rest.starts_with('<') && next.is_ascii_alphabetic() {
+            let end = i + tag_end(rest);
+            let name = tag_name(&html[i + 1..end]);
+            let mut element = Element {
+                name,
+                open: i..end,
+                children: Vec::new(),
+                span: i..end,
+                closed: true,
+            };
+            if html[..end].ends_with("/>") || VOID.contains(&element.name.as_str()) {
+                push(&mut stack, &mut roots, Node::Element(element));
+            } else if RAW.contains(&element.name.as_str()) {
+                let closing = format!("</{}", element.name);
+                let (content_end, span_end) = match html[end..].to_ascii_lowercase().find(&closing)
+                {
+                    Some(at) => {
+                        let after = end + at;
+                        (
+                            after,
+                            html[after..]
+                                .find('>')
+                                .map_or(html.len(), |e| after + e + 1),
+                        )
+                    }
+                    None => {
+                        element.closed = false;
+                        (html.len(), html.len())
+                    }
+                };
+                if content_end > end {
+                    element.children.push(Node::Text);
+                }
+                element.span = i..span_end;
+                push(&mut stack, &mut roots, Node::Element(element));
+                i = span_end;
+                continue;
+            } else {
+                stack.push(element);
+            }
+            i = end;
+        } else {
+            let skip = rest.chars().next().map_or(1, char::len_utf8);
+            let end = rest[skip..]
+                .find('<')
+                .map_or(html.len(), |end| i + skip + end);
+            push(&mut stack, &mut roots, Node::Text);
+            i = end;
+        }
+    }
+    while !stack.is_empty() {
+        close(&mut stack, &mut roots, html.len(), false);
+    }
+    roots
+}
+
+fn push(stack: &mut [Element], roots: &mut Vec<Node>, node: Node) {
+    match stack.last_mut() {
+        Some(parent) => parent.children.push(node),
+        None => roots.push(node),
+    }
+}
+
+fn close(stack: &mut Vec<Element>, roots: &mut Vec<Node>, end: usize, closed: bool) {
+    let Some(mut element) = stack.pop() else {
+        return;
+    };
+    element.span.end = end;
+    element.closed = closed;
+    push(stack, roots, Node::Element(element));
+}
+
+fn tag_name(tag: &str) -> String {
+    tag.split(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+/// Returns the length of the opening tag at the start of `rest`, skipping quoted values.
+fn tag_end(rest: &str) -> usize {
+    let mut quote = None;
+    for (i, c) in rest.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return i + 1,
+            _ => {}
+        }
+    }
+    rest.len()
+}
+
+fn is_block_only(nodes: &[Node]) -> bool {
+    !nodes.is_empty()
+        && nodes.iter().all(|node| match node {
+            Node::Element(element) => element.closed && BLOCK.contains(&element.name.as_str()),
+            Node::Text => false,
+            Node::Other(_) => true,
+        })
+}
+
+fn write_lines(out: &mut String, html: &str, nodes: &[Node], depth: usize) {
+    for (i, node) in nodes.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        out.push_str(&"  ".repeat(depth));
+        match node {
+            Node::Element(element) => write_element(out, html, element, depth),
+            Node::Other(range) => out.push_str(&html[range.clone()]),
+            Node::Text => unreachable!(),
+        }
+    }
+}
+
+fn write_element(out: &mut String, html: &str, element: &Element, depth: usize) {
+    if RAW.contains(&element.name.as_str()) || !is_block_only(&element.children) {
+        out.push_str(&html[element.span.clone()]);
+        return;
+    }
+    let inner_start = element.open.end;
+    let close_start = match element.children.last() {
+        Some(Node::Element(last)) => last.span.end,
+        Some(Node::Other(range)) => range.end,
+        _ => inner_start,
+    };
+    out.push_str(&html[element.open.clone()]);
+    out.push('\n');
+    write_lines(out, html, &element.children, depth + 1);
+    out.push('\n');
+    out.push_str(&"  ".repeat(depth));
+    out.push_str(&html[close_start..element.span.end]);
+}
@@ -0,0 +1,453 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+use serde_json::Value;
+
+use super::parse::{BinOp, Expr, ExprKind, Method, Node, Pat, UnOp};
+use super::Error;
+use crate::Buffer;
+
+/// Longest range a template can build, as ranges are collected into arrays.
+const MAX_RANGE_LEN: i128 = 100_000;
+
+/// Renders nodes, holding the variables bound by `for` and `match`.
+pub(super) struct Renderer<'a> {
+    data: &'a Value,
+    /// Markup rendered in place of `@name;`, for the props of hot reloaded components.
+    slots: &'a [(&'a str, String)],
+    scopes: Vec<(String, Value)>,
+}
+
+impl<'a> Renderer<'a> {
+    pub(super) fn new(data: &'a Value, slots: &'a [(&'a str, String)]) -> Self {
+        Self {
+            data,
+            slots,
+            scopes: Vec::new(),
+        }
+    }
+
+    pub(super) fn render(&mut self, out: &mut Buffer, nodes: &[Node]) -> Result<(), Error> {
+        for node in nodes {
+            match node {
+                Node::Text(text) => out.push_str(text),
+                Node::Expr { expr, escape } => {
+                    if let Some(slot) = self.slot(expr) {
+                        out.push_str(slot);
+                        continue;
+                    }
+                    let value = self.eval(expr)?;
+                    push_text(out, &text(&value, expr)?, *escape);
+                }
+                Node::Attribute(expr) => {
+                    let value = self.eval(expr)?;
+                    out.push_str(&escape_attribute(&text(&value, expr)?));
+                }
+                Node::Cond { branches, else_ } => {
+                    let mut body = else_;
+                    for (cond, branch) in branches {
+                        if self.eval_bool(cond)? {
+                            body = branch;
+                            break;
+                        }
+                    }
+                    self.render(out, body)?;
+                }
+                Node::For { pat, expr, body } => {
+                    let items = match self.eval(expr)?.into_owned() {
+                        Value::Array(items) => items,
+                        Value::Object(map) => map
+                            .into_iter()
+                            .map(|(key, value)| Value::Array(vec![Value::String(key), value]))
+                            .collect(),
+                        value => {
+                            return Err(Error::at(
+                                expr.location,
+                                format!("Cannot iterate over {}", type_name(&value)),
+                            ))
+                        }
+                    };
+                    for item in &items {
+                        let len = self.scopes.len();
+                        if !self.bind(pat, item) {
+                            return Err(Error::at(
+                                expr.location,
+                                format!("Pattern does not match {}", type_name(item)),
+                            ));
+                        }
+                        let result = self.render(out, body);
+                        self.scopes.truncate(len);
+                        result?;
+                    }
+                }
+                Node::Match { expr, arms } => {
+                    let value = self.eval(expr)?.into_owned();
+                    let mut matched = false;
+                    for arm in arms {
+                        let len = self.scopes.len();
+                        let result = if !self.bind(&arm.pat, &value) {
+                            Ok(false)
+                        } else {
+                            match &arm.guard {
+                                Some(guard) => self.eval_bool(guard),
+                                None => Ok(true),
+                            }
+                        }
+                        .and_then(|is_match| {
+                            if is_match {
+                                self.render(out, &arm.body)?;
+                            }
+                            Ok(is_match)
+                        });
+                        self.scopes.truncate(len);
+                        if result? {
+                            matched = true;
+                            break;
+                        }
+                    }
+                    if !matched {
+                        return Err(Error::at(expr.location, "No match arm matches the value"));
+                    }
+                }
+                Node::Outlet(name) => out.outlet(name.clone()),
+                Node::Nonce(directive) => {
+                    out.push_nonce(*directive);
+                }
+                Node::Portal { name, body } => {
+                    let start = out.len();
+                    self.render(out, body)?;
+                    out.portal(name.clone(), start);
+                }
+                Node::Once { key, outlet, body } => {
+                    if out.once(*key) {
+                        let start = out.len();
+                        self.render(out, body)?;
+                        if let Some(outlet) = outlet {
+                            out.portal(outlet.clone(), start);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the slot rendered by `expr`, if it is a variable not bound by the template.
+    fn slot(&self, expr: &Expr) -> Option<&'a str> {
+        let ExprKind::Var(name) = &expr.kind else {
+            return None;
+        };
+        if self.scopes.iter().any(|(n, _)| n == name) {
+            return None;
+        }
+        self.slots
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, slot)| slot.as_str())
+    }
+
+    /// Binds the variables of `pat` to `value`, returning whether it matches.
+    fn bind(&mut self, pat: &Pat, value: &Value) -> bool {
+        match pat {
+            Pat::Wild => true,
+            Pat::Bind(name) => {
+                self.scopes.push((name.clone(), value.clone()));
+                true
+            }
+            Pat::Lit(lit) => equals(lit, value),
+            Pat::Tuple(pats) => match value {
+                Value::Array(items) if items.len() == pats.len() => pats
+                    .iter()
+                    .zip(items)
+                    .all(|(pat, item)| self.bind(pat, item)),
+                _ => false,
+            },
+            Pat::Or(pats) => pats.iter().any(|pat| {
+                let len = self.scopes.len();
+                let is_match = self.bind(pat, value);
+                if !is_match {
+                    self.scopes.truncate(len);
+                }
+                is_match
+            }),
+        }
+    }
+
+    fn eval_bool(&self, expr: &Expr) -> Result<bool, Error> {
+        match *self.eval(expr)? {
+            Value::Bool(value) => Ok(value),
+            ref value => Err(Error::at(
+                expr.location,
+                format!("Expected a boolean, found {}", type_name(value)),
+            )),
+        }
+    }
+
+    /// Evaluates `expr`, borrowing values of the data and of variables where possible.
+    fn eval(&self, expr: &Expr) -> Result<Cow<'_, Value>, Error> {
+        let error = |message: String| Err(Error::at(expr.location, message));
+        let value = match &expr.kind {
+            ExprKind::Lit(value) => value.clone(),
+            ExprKind::Var(name) => {
+                let scoped = self.scopes.iter().rev().find(|(n, _)| n == name);
+                return match scoped
+                    .map(|(_, value)| value)
+                    .or_else(|| self.data.get(name))
+                {
+                    Some(value) => Ok(Cow::Borrowed(value)),
+                    None => error(format!("Unknown variable `{}`", name)),
+                };
+            }
+            ExprKind::Field(base, name) => {
+                let base = self.eval(base)?;
+                return match base {
+                    Cow::Borrowed(Value::Object(map)) => {
+                        Ok(map.get(name).map_or(Cow::Owned(Value::Null), Cow::Borrowed))
+                    }
+                    Cow::Owned(Value::Object(mut map)) => {
+                        Ok(Cow::Owned(map.remove(name).unwrap_or(Value::Null)))
+                    }
+                    value if value.is_null() => Ok(value),
+                    value => error(format!("No field `{}` on {}", name, type_name(&value))),
+                };
+            }
+            ExprKind::Index(base, index) => {
+                let (base, index) = (self.eval(base)?, self.eval(index)?);
+                let found = match (&*base, &*index) {
+                    (Value::Array(items), Value::Number(n)) => {
+                        match n.as_u64().filter(|i| (*i as usize) < items.len()) {
+                            Some(i) => i as usize,
+                            None => return error(format!("Index {} is out of bounds", n)),
+                        }
+                    }
+                    (Value::Object(_), Value::String(_)) | (Value::Null, _) => 0,
+                    (value, index) => {
+                        return error(format!(
+                            "Cannot index {} with {}",
+                            type_name(value),
+                            type_name(index)
+                        ))
+                    }
+                };
+                return Ok(match (base, &*index) {
+                    (Cow::Borrowed(Value::Array(items)), _) => Cow::Borrowed(&items[found]),
+                    (Cow::Owned(Value::Array(mut items)), _) => {
+                        Cow::Owned(items.swap_remove(found))
+                    }
+                    (Cow::Borrowed(Value::Object(map)), Value::String(key)) => {
+                        map.get(key).map_or(Cow::Owned(Value::Null), Cow::Borrowed)
+                    }
+                    (Cow::Owned(Value::Object(mut map)), Value::String(key)) => {
+                        Cow::Owned(map.remove(key).unwrap_or(Value::Null))
+                    }
+                    _ => Cow::Owned(Value::Null),
+                });
+            }
+            ExprKind::Method(receiver, method) => {
+                return call(*method, self.eval(receiver)?)
+                    .map_err(|message| Error::at(expr.location, message));
+            }
+            ExprKind::Unary(op, operand) => match (op, &*self.eval(operand)?) {
+                (UnOp::Not, Value::Bool(value)) => Value::Bool(!value),
+                (UnOp::Neg, Value::Number(n)) => match n.as_i64() {
+                    Some(n) => match n.checked_neg() {
+                        Some(n) => Value::from(n),
+                        None => return error(format!("Cannot negate {}, it overflows", n)),
+                    },
+                    None => Value::from(-n.as_f64().unwrap_or_default()),
+                },
+                (UnOp::Not, value) => {
+                    return error(format!("Cannot apply `!` to {}", type_name(value)))
+                }
+                (UnOp::Neg, value) => return error(format!("Cannot negate {}", type_name(value))),
+            },
+            ExprKind::Binary(BinOp::And, left, right) => {
+                Value::Bool(self.eval_bool(left)? && self.eval_bool(right)?)
+            }
+            ExprKind::Binary(BinOp::Or, left, right) => {
+                Value::Bool(self.eval_bool(left)? || self.eval_bool(right)?)
+            }
+            ExprKind::Binary(op, left, right) => {
+                let (left, right) = (self.eval(left)?, self.eval(right)?);
+                match binary(*op, &left, &right) {
+                    Some(value) => value,
+                    None => {
+                        return error(format!(
+                            "Cannot apply `{}` to {} and {}",
+                            op.symbol(),
+                            type_name(&left),
+                            type_name(&right)
+                        ))
+                    }
+                }
+            }
+            ExprKind::Array(items) => Value::Array(
+                items
+                    .iter()
+                    .map(|item| self.eval(item).map(Cow::into_owned))
+                    .collect::<Result<_, _>>()?,
+            ),
+            ExprKind::Range {
+                start,
+                end,
+                inclusive,
+            } => match (self.eval(start)?.as_i64(), self.eval(end)?.as_i64()) {
+                (Some(start), Some(end)) => {
+                    let len = (i128::from(end) - i128::from(start) + i128::from(*inclusive)).max(0);
+                    if len > MAX_RANGE_LEN {
+                        return error(format!(
+                            "Range of {} items is longer than the limit of {}",
+                            len, MAX_RANGE_LEN
+                        ));
+                    }
+                    (start..end)
+                        .chain((*inclusive && start <= end).then_some(end))
+                        .map(Value::from)
+                        .collect()
+                }
+                _ => return error("Range bounds must be integers".to_owned()),
+            },
+        };
+        Ok(Cow::Owned(value))
+    }
+}
+
+fn call(method: Method, value: Cow<'_, Value>) -> Result<Cow<'_, Value>, String> {
+    let result = match (method, &*value) {
+        (Method::Iter, Value::Array(_) | Value::Object(_)) => return Ok(value),
+        (Method::Enumerate, Value::Array(items)) => items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| Value::Array(vec![Value::from(i), item.clone()]))
+            .collect(),
+        (Method::Rev, Value::Array(items)) => items.iter().rev().cloned().collect(),
+        (Method::Keys, Value::Object(map)) => map.keys().cloned().map(Value::from).collect(),
+        (Method::Values, Value::Object(map)) => map.values().cloned().collect(),
+        (Method::Len, Value::Array(items)) => Value::from(items.len()),
+        (Method::Len, Value::Object(map)) => Value::from(map.len()),
+        (Method::Len, Value::String(s)) => Value::from(s.len()),
+        (Method::IsEmpty, Value::Array(items)) => Value::Bool(items.is_empty()),
+        (Method::IsEmpty, Value::Object(map)) => Value::Bool(map.is_empty()),
+        (Method::IsEmpty, Value::String(s)) => Value::Bool(s.is_empty()),
+        (Method::ToString, value @ (Value::String(_) | Value::Number(_) | Value::Bool(_))) => {
+            Value::from(to_text(value).unwrap_or_default().into_owned())
+        }
+        (Method::ToUppercase, Value::String(s)) => Value::from(s.to_uppercase()),
+        (Method::ToLowercase, Value::String(s)) => Value::from(s.to_lowercase()),
+        (Method::Trim, Value::String(s)) => Value::from(s.trim()),
+        (method, value) => {
+            return Err(format!(
+                "No method `{}` on {}",
+                method.name(),
+                type_name(value)
+            ))
+        }
+    };
+    Ok(Cow::Owned(result))
+}
+
+fn binary(op: BinOp, left: &Value, right: &Value) -> Option<Value> {
+    match op {
+        BinOp::Eq => return Some(Value::Bool(equals(left, right))),
+        BinOp::Ne => return Some(Value::Bool(!equals(left, right))),
+        BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => {
+            let ordering = match (left, right) {
+                (Value::String(a), Value::String(b)) => a.cmp(b),
+                (Value::Number(a), Value::Number(b)) => match (a.as_i64(), b.as_i64()) {
+                    (Some(a), Some(b)) => a.cmp(&b),
+                    _ => a.as_f64()?.partial_cmp(&b.as_f64()?)?,
+                },
+                _ => return None,
+            };
+            return Some(Value::Bool(match op {
+                BinOp::Lt => ordering == Ordering::Less,
+                BinOp::Le => ordering != Ordering::Greater,
+                BinOp::Gt => ordering == Ordering::Greater,
+                _ => ordering != Ordering::Less,
+            }));
+        }
+        _ => {}
+    }
+    if let (BinOp::Add, Value::String(a), Value::String(b)) = (op, left, right) {
+        return Some(Value::from(format!("{}{}", a, b)));
+    }
+    let (Value::Number(a), Value::Number(b)) = (left, right) else {
+        return None;
+    };
+    if let (Some(a), Some(b)) = (a.as_i64(), b.as_i64()) {
+        return match op {
+            BinOp::Add => a.checked_add(b),
+            BinOp::Sub => a.checked_sub(b),
+            BinOp::Mul => a.checked_mul(b),
+            BinOp::Div => a.checked_div(b),
+            BinOp::Rem => a.checked_rem(b),
+            _ => None,
+        }
+        .map(Value::from);
+    }
+    let (a, b) = (a.as_f64()?, b.as_f64()?);
+    Some(Value::from(match op {
+        BinOp::Add => a + b,
+        BinOp::Sub => a - b,
+        BinOp::Mul => a * b,
+        BinOp::Div => a / b,
+        BinOp::Rem => a % b,
+        _ => return None,
+    }))
+}
+
+/// Compares values, numbers by their value regardless of their representation.
+fn equals(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => match (a.as_i64(), b.as_i64()) {
+            (Some(a), Some(b)) => a == b,
+            _ => a.as_f64() == b.as_f64(),
+        },
+        (a, b) => a == b,
+    }
+}
+
+fn to_text(value: &Value) -> Option<Cow<'_, str>> {
+    Some(match value {
+        Value::String(s) => Cow::Borrowed(s),
+        Value::Number(n) => Cow::Owned(n.to_string()),
+        Value::Bool(b) => Cow::Owned(b.to_string()),
+        Value::Null => Cow::Borrowed(""),
+        Value::Array(_) | Value::Object(_) => return None,
+    })
+}
+
+fn text<'v>(value: &'v Value, expr: &Expr) -> Result<Cow<'v, str>, Error> {
+    to_text(value)
+        .ok_or_else(|| Error::at(expr.location, format!("Cannot render {}", type_name(value))))
+}
+
+#[allow(unused_variables)]
+fn push_text(out: &mut Buffer, text: &str, escape: bool) {
+    #[cfg(feature = "html_escape")]
+    if escape {
+        html_escape::encode_text_to_string(text, out);
+        return;
+    }
+    out.push_str(text);
+}
+
+/// Escapes a value of a double-quoted attribute, whatever the `html_escape` feature, so that data
+/// can't end the attribute.
+pub(super) fn escape_attribute(value: &str) -> Cow<'_, str> {
+    if !value.contains(['&', '"']) {
+        return Cow::Borrowed(value);
+    }
+    Cow::Owned(value.replace('&', "&amp;").replace('"', "&quot;"))
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
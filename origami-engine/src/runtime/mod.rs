@@ -0,0 +1,154 @@
+//! Templates parsed and rendered at runtime.
+//!
+//! A [`Template`] is parsed from the same syntax as the body of [`comp!`](crate::comp), and
+//! rendered against data serialized to a [`serde_json::Value`]. Expressions are restricted to
+//! reading the data: variables, fields, indexing, literals, operators and a few methods, so
+//! templates can be edited by people who should not run arbitrary code.
+//!
+//! Components, `#provide`, `#include` and `src_file` are not available at runtime.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{Buffer, Origami};
+
+mod eval;
+mod parse;
+
+use self::parse::Node;
+
+/// Error raised while parsing or rendering a [`Template`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    message: String,
+    location: Option<(usize, usize)>,
+}
+
+impl Error {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            location: None,
+        }
+    }
+
+    fn at(location: proc_macro2::LineColumn, message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            location: Some((location.line, location.column + 1)),
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Line and column in the template source, if the error is tied to a location.
+    pub fn location(&self) -> Option<(usize, usize)> {
+        self.location
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.location {
+            Some((line, column)) => write!(f, "{}:{}: {}", line, column, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<syn::Error> for Error {
+    fn from(error: syn::Error) -> Self {
+        Self::at(error.span().start(), error.to_string())
+    }
+}
+
+/// A parsed template, rendered any number of times.
+#[derive(Debug, Clone)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, Error> {
+        Ok(Self {
+            nodes: parse::parse(source)?,
+        })
+    }
+
+    /// Renders the template with `data`, whose fields are the variables of the template.
+    pub fn render<T: Serialize + ?Sized>(&self, data: &T) -> Result<Origami, Error> {
+        let data = serde_json::to_value(data).map_err(|e| Error::new(e.to_string()))?;
+        let mut out = Buffer::new();
+        self.render_to(&mut out, &data)?;
+        Ok(Origami::from(out))
+    }
+
+    pub fn render_to(&self, out: &mut Buffer, data: &Value) -> Result<(), Error> {
+        eval::Renderer::new(data, &[]).render(out, &self.nodes)
+    }
+
+    /// Renders the template with markup for some variables, rendered without escaping.
+    #[cfg(feature = "hot_reload")]
+    pub(crate) fn render_with_slots(
+        &self,
+        out: &mut Buffer,
+        data: &Value,
+        slots: &[(&str, String)],
+    ) -> Result<(), Error> {
+        eval::Renderer::new(data, slots).render(out, &self.nodes)
+    }
+}
+
+impl FromStr for Template {
+    type Err = Error;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        Self::parse(source)
+    }
+}
+
+/// Named templates, parsed once and shared between threads.
+#[derive(Debug, Default)]
+pub struct Templates {
+    templates: RwLock<HashMap<String, Arc<Template>>>,
+}
+
+impl Templates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses `source` and stores it as `name`, replacing the previous template of that name.
+    pub fn insert(&self, name: impl Into<String>, source: &str) -> Result<Arc<Template>, Error> {
+        let template = Arc::new(Template::parse(source)?);
+        self.templates
+            .write()
+            .unwrap()
+            .insert(name.into(), template.clone());
+        Ok(template)
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<Template>> {
+        self.templates.read().unwrap().get(name).cloned()
+    }
+
+    pub fn remove(&self, name: &str) -> Option<Arc<Template>> {
+        self.templates.write().unwrap().remove(name)
+    }
+
+    pub fn render<T: Serialize + ?Sized>(&self, name: &str, data: &T) -> Result<Origami, Error> {
+        match self.get(name) {
+            Some(template) => template.render(data),
+            None => Err(Error::new(format!("Unknown template `{}`", name))),
+        }
+    }
+}
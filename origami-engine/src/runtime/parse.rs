@@ -0,0 +1,590 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use origami_syntax::{
+    parse_childrens, AttributeKey, AttributeValue, Attributes, Children, Context, HtmlChildrens,
+    Once,
+};
+use proc_macro2::{LineColumn, TokenStream};
+use quote::ToTokens;
+use serde_json::Value;
+use syn::parse::{ParseStream, Parser as _};
+use syn::spanned::Spanned;
+use syn::{Lit, LitStr};
+
+use super::eval::escape_attribute;
+use super::Error;
+use crate::csp::Directive;
+
+macro_rules! bail {
+    ($span:expr, $message:expr) => {
+        return Err(syn::Error::new($span.span(), $message))
+    };
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum Node {
+    Text(String),
+    Expr {
+        expr: Expr,
+        escape: bool,
+    },
+    /// Value of an attribute, which is not escaped, as in [`comp!`](crate::comp).
+    Attribute(Expr),
+    Cond {
+        branches: Vec<(Expr, Vec<Node>)>,
+        else_: Vec<Node>,
+    },
+    For {
+        pat: Pat,
+        expr: Expr,
+        body: Vec<Node>,
+    },
+    Match {
+        expr: Expr,
+        arms: Vec<Arm>,
+    },
+    Outlet(String),
+    /// `nonce` attribute with the nonce of the render context.
+    Nonce(Directive),
+    Portal {
+        name: String,
+        body: Vec<Node>,
+    },
+    Once {
+        key: u64,
+        outlet: Option<String>,
+        body: Vec<Node>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Arm {
+    pub(super) pat: Pat,
+    pub(super) guard: Option<Expr>,
+    pub(super) body: Vec<Node>,
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Expr {
+    pub(super) kind: ExprKind,
+    pub(super) location: LineColumn,
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum ExprKind {
+    Lit(Value),
+    Var(String),
+    Field(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+    Method(Box<Expr>, Method),
+    Unary(UnOp, Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+    Array(Vec<Expr>),
+    Range {
+        start: Box<Expr>,
+        end: Box<Expr>,
+        inclusive: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) enum UnOp {
+    Not,
+    Neg,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    And,
+    Or,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// Methods callable in expressions, all without arguments.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Method {
+    Iter,
+    Enumerate,
+    Rev,
+    Keys,
+    Values,
+    Len,
+    IsEmpty,
+    ToString,
+    ToUppercase,
+    ToLowercase,
+    Trim,
+}
+
+impl BinOp {
+    pub(super) fn symbol(self) -> &'static str {
+        match self {
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::Rem => "%",
+            Self::And => "&&",
+            Self::Or => "||",
+            Self::Eq => "==",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+        }
+    }
+}
+
+impl Method {
+    pub(super) fn name(self) -> &'static str {
+        match self {
+            Self::Iter => "iter",
+            Self::Enumerate => "enumerate",
+            Self::Rev => "rev",
+            Self::Keys => "keys",
+            Self::Values => "values",
+            Self::Len => "len",
+            Self::IsEmpty => "is_empty",
+            Self::ToString => "to_string",
+            Self::ToUppercase => "to_uppercase",
+            Self::ToLowercase => "to_lowercase",
+            Self::Trim => "trim",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum Pat {
+    Wild,
+    Bind(String),
+    Lit(Value),
+    Tuple(Vec<Pat>),
+    Or(Vec<Pat>),
+}
+
+pub(super) fn parse(source: &str) -> Result<Vec<Node>, Error> {
+    let ts =
+        TokenStream::from_str(source).map_err(|e| Error::at(e.span().start(), e.to_string()))?;
+    let mut context = Context {
+        read_files: false,
+        ..Default::default()
+    };
+    let childrens = (|input: ParseStream| parse_childrens(input, &mut context)).parse2(ts)?;
+    Ok(lower_block(&childrens)?)
+}
+
+/// Nodes of a block, with adjacent text merged.
+#[derive(Default)]
+struct Nodes(Vec<Node>);
+
+impl Nodes {
+    fn text(&mut self, text: &str) {
+        if let Some(Node::Text(last)) = self.0.last_mut() {
+            last.push_str(text);
+        } else {
+            self.0.push(Node::Text(text.to_owned()));
+        }
+    }
+
+    fn push(&mut self, node: Node) {
+        self.0.push(node);
+    }
+}
+
+fn lower_block(childrens: &[Children]) -> syn::Result<Vec<Node>> {
+    let mut nodes = Nodes::default();
+    lower_childrens(&mut nodes, childrens)?;
+    Ok(nodes.0)
+}
+
+/// Lowers the syntax tree of `origami-syntax` into nodes, escaping and minifying text ahead of
+/// rendering.
+fn lower_childrens(nodes: &mut Nodes, childrens: &[Children]) -> syn::Result<()> {
+    for children in childrens {
+        match children {
+            Children::Text { text, .. } => {
+                nodes.text(&escape_text(&text.value(), escapes(children)))
+            }
+            Children::Expr { expr, .. } => nodes.push(Node::Expr {
+                expr: convert_expr(expr)?,
+                escape: escapes(children),
+            }),
+            Children::CompCall { comp, .. } => {
+                bail!(comp, "Components are not supported in runtime templates")
+            }
+            Children::Cond {
+                if_,
+                else_ifs,
+                else_,
+            } => {
+                let branches = std::iter::once(if_)
+                    .chain(else_ifs)
+                    .map(|(cond, body)| Ok((convert_expr(cond)?, lower_block(body)?)))
+                    .collect::<syn::Result<_>>()?;
+                nodes.push(Node::Cond {
+                    branches,
+                    else_: lower_block(else_)?,
+                });
+            }
+            Children::For {
+                expr_b,
+                expr_a,
+                childrens,
+            } => nodes.push(Node::For {
+                pat: convert_expr_pat(expr_b)?,
+                expr: convert_expr(expr_a)?,
+                body: lower_block(childrens)?,
+            }),
+            Children::Html {
+                tag,
+                attrs,
+                childrens,
+            } => {
+                nodes.text(&format!("<{}", tag));
+                lower_attributes(nodes, attrs)?;
+                match childrens {
+                    HtmlChildrens::Childrens(childrens) => {
+                        nodes.text(">");
+                        lower_childrens(nodes, childrens)?;
+                        nodes.text(&format!("</{}>", tag));
+                    }
+                    HtmlChildrens::SelfClosing => nodes.text("/>"),
+                }
+            }
+            Children::Match { expr, arms } => nodes.push(Node::Match {
+                expr: convert_expr(expr)?,
+                arms: arms
+                    .iter()
+                    .map(|arm| {
+                        Ok(Arm {
+                            pat: convert_pat(&arm.pat)?,
+                            guard: arm
+                                .guard
+                                .as_ref()
+                                .map(|(_, guard)| convert_expr(guard))
+                                .transpose()?,
+                            body: lower_block(&arm.body)?,
+                        })
+                    })
+                    .collect::<syn::Result<_>>()?,
+            }),
+            Children::Outlet { name } => nodes.push(Node::Outlet(name.to_string())),
+            Children::Portal { name, childrens } => nodes.push(Node::Portal {
+                name: name.to_string(),
+                body: lower_block(childrens)?,
+            }),
+            Children::Provide { expr, .. } => {
+                bail!(expr, "`#provide` is not supported in runtime templates")
+            }
+            Children::Scope { childrens } => lower_childrens(nodes, childrens)?,
+            Children::Script {
+                text,
+                attrs,
+                nonce,
+                once,
+                ..
+            } => lower_block_tag(nodes, "script", text, attrs, *nonce, once, children)?,
+            Children::Style {
+                text,
+                attrs,
+                nonce,
+                once,
+                ..
+            } => lower_block_tag(nodes, "style", text, attrs, *nonce, once, children)?,
+            Children::Include { src_file, .. } => {
+                bail!(src_file, "`#include` is not supported in runtime templates")
+            }
+        }
+    }
+    Ok(())
+}
+
+#[allow(unused_variables)]
+fn escapes(children: &Children) -> bool {
+    #[cfg(feature = "html_escape")]
+    if let Children::Text { escape, .. } | Children::Expr { escape, .. } = children {
+        return *escape;
+    }
+    false
+}
+
+fn lower_block_tag(
+    nodes: &mut Nodes,
+    tag: &str,
+    text: &Option<LitStr>,
+    attrs: &Attributes,
+    nonce: bool,
+    once: &Option<Once>,
+    children: &Children,
+) -> syn::Result<()> {
+    let mut block = Nodes::default();
+    block.text(&format!("<{}", tag));
+    lower_attributes(&mut block, attrs)?;
+    if nonce {
+        block.push(Node::Nonce(if tag == "script" {
+            Directive::Script
+        } else {
+            Directive::Style
+        }));
+    }
+    block.text(">");
+    if let Some(text) = text {
+        block.text(&minify_text(&text.value(), tag, children));
+    }
+    block.text(&format!("</{}>", tag));
+    let Some(once) = once else {
+        for node in block.0 {
+            match node {
+                Node::Text(text) => nodes.text(&text),
+                node => nodes.push(node),
+            }
+        }
+        return Ok(());
+    };
+    // The key is the hash of the content of the block, independent of where it appears.
+    let mut hasher = DefaultHasher::new();
+    tag.hash(&mut hasher);
+    for (key, value) in &attrs.0 {
+        attribute_name(key).hash(&mut hasher);
+        if let Some(value) = value {
+            match value {
+                AttributeValue::LitStr(lit) => lit.value().hash(&mut hasher),
+                AttributeValue::Expr(expr) => expr.to_token_stream().to_string().hash(&mut hasher),
+            }
+        }
+    }
+    text.as_ref().map(LitStr::value).hash(&mut hasher);
+    nodes.push(Node::Once {
+        key: hasher.finish(),
+        outlet: once.outlet.as_ref().map(ToString::to_string),
+        body: block.0,
+    });
+    Ok(())
+}
+
+fn attribute_name(key: &AttributeKey) -> Option<String> {
+    match key {
+        AttributeKey::Ident(ident) => Some(ident.to_string()),
+        AttributeKey::LitStr(lit) => Some(lit.value()),
+        #[allow(unreachable_patterns)]
+        _ => None,
+    }
+}
+
+fn lower_attributes(nodes: &mut Nodes, attrs: &Attributes) -> syn::Result<()> {
+    for (key, value) in &attrs.0 {
+        let Some(name) = attribute_name(key) else {
+            continue;
+        };
+        nodes.text(&format!(" {}", name));
+        match value {
+            Some(AttributeValue::LitStr(text)) => {
+                nodes.text(&format!("=\"{}\"", escape_attribute(&text.value())))
+            }
+            Some(AttributeValue::Expr(expr)) => {
+                nodes.text("=\"");
+                nodes.push(Node::Attribute(convert_expr(expr)?));
+                nodes.text("\"");
+            }
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+#[allow(unused_variables)]
+fn escape_text(text: &str, escape: bool) -> String {
+    #[cfg(feature = "html_escape")]
+    if escape {
+        return html_escape::encode_text(text).into_owned();
+    }
+    text.to_owned()
+}
+
+#[allow(unused_variables)]
+fn minify_text(text: &str, tag: &str, children: &Children) -> String {
+    #[cfg(feature = "minify_html")]
+    if let Children::Script {
+        minify: Some(options),
+        ..
+    }
+    | Children::Style {
+        minify: Some(options),
+        ..
+    } = children
+    {
+        let mut cfg = minify_html::Cfg {
+            minify_js: tag == "script",
+            minify_css: tag == "style",
+            ..Default::default()
+        };
+        options.apply(&mut cfg);
+        return String::from_utf8_lossy(&minify_html::minify(text.as_bytes(), &cfg)).into_owned();
+    }
+    text.to_owned()
+}
+
+fn convert_expr(expr: &syn::Expr) -> syn::Result<Expr> {
+    let boxed = |expr: &syn::Expr| convert_expr(expr).map(Box::new);
+    let kind = match expr {
+        syn::Expr::Lit(lit) => ExprKind::Lit(convert_lit(&lit.lit)?),
+        syn::Expr::Path(path) if path.qself.is_none() => match path.path.get_ident() {
+            Some(ident) => ExprKind::Var(ident.to_string()),
+            None => bail!(
+                path,
+                "Only plain variables are supported in runtime templates"
+            ),
+        },
+        syn::Expr::Field(field) => match &field.member {
+            syn::Member::Named(name) => ExprKind::Field(boxed(&field.base)?, name.to_string()),
+            syn::Member::Unnamed(index) => ExprKind::Index(
+                boxed(&field.base)?,
+                Box::new(Expr {
+                    kind: ExprKind::Lit(Value::from(index.index)),
+                    location: index.span.start(),
+                }),
+            ),
+        },
+        syn::Expr::Index(index) => ExprKind::Index(boxed(&index.expr)?, boxed(&index.index)?),
+        syn::Expr::MethodCall(call) if call.args.is_empty() && call.turbofish.is_none() => {
+            let method = match call.method.to_string().as_str() {
+                "iter" | "into_iter" => Method::Iter,
+                "enumerate" => Method::Enumerate,
+                "rev" => Method::Rev,
+                "keys" => Method::Keys,
+                "values" => Method::Values,
+                "len" => Method::Len,
+                "is_empty" => Method::IsEmpty,
+                "to_string" => Method::ToString,
+                "to_uppercase" => Method::ToUppercase,
+                "to_lowercase" => Method::ToLowercase,
+                "trim" => Method::Trim,
+                method => bail!(
+                    call.method,
+                    format!("Unsupported method `{}` in runtime templates", method)
+                ),
+            };
+            ExprKind::Method(boxed(&call.receiver)?, method)
+        }
+        syn::Expr::Unary(unary) => match unary.op {
+            syn::UnOp::Not(_) => ExprKind::Unary(UnOp::Not, boxed(&unary.expr)?),
+            syn::UnOp::Neg(_) => ExprKind::Unary(UnOp::Neg, boxed(&unary.expr)?),
+            _ => return convert_expr(&unary.expr),
+        },
+        syn::Expr::Reference(reference) => return convert_expr(&reference.expr),
+        syn::Expr::Paren(paren) => return convert_expr(&paren.expr),
+        syn::Expr::Group(group) => return convert_expr(&group.expr),
+        syn::Expr::Binary(binary) => {
+            let op = match binary.op {
+                syn::BinOp::Add(_) => BinOp::Add,
+                syn::BinOp::Sub(_) => BinOp::Sub,
+                syn::BinOp::Mul(_) => BinOp::Mul,
+                syn::BinOp::Div(_) => BinOp::Div,
+                syn::BinOp::Rem(_) => BinOp::Rem,
+                syn::BinOp::And(_) => BinOp::And,
+                syn::BinOp::Or(_) => BinOp::Or,
+                syn::BinOp::Eq(_) => BinOp::Eq,
+                syn::BinOp::Ne(_) => BinOp::Ne,
+                syn::BinOp::Lt(_) => BinOp::Lt,
+                syn::BinOp::Le(_) => BinOp::Le,
+                syn::BinOp::Gt(_) => BinOp::Gt,
+                syn::BinOp::Ge(_) => BinOp::Ge,
+                _ => bail!(binary.op, "Unsupported operator in runtime templates"),
+            };
+            ExprKind::Binary(op, boxed(&binary.left)?, boxed(&binary.right)?)
+        }
+        syn::Expr::Array(array) => ExprKind::Array(
+            array
+                .elems
+                .iter()
+                .map(convert_expr)
+                .collect::<syn::Result<_>>()?,
+        ),
+        syn::Expr::Tuple(tuple) => ExprKind::Array(
+            tuple
+                .elems
+                .iter()
+                .map(convert_expr)
+                .collect::<syn::Result<_>>()?,
+        ),
+        syn::Expr::Range(range) => match (&range.start, &range.end) {
+            (Some(start), Some(end)) => ExprKind::Range {
+                start: boxed(start)?,
+                end: boxed(end)?,
+                inclusive: matches!(range.limits, syn::RangeLimits::Closed(_)),
+            },
+            _ => bail!(range, "Ranges in runtime templates need a start and an end"),
+        },
+        expr => bail!(expr, "Unsupported expression in runtime templates"),
+    };
+    Ok(Expr {
+        kind,
+        location: expr.span().start(),
+    })
+}
+
+fn convert_lit(lit: &Lit) -> syn::Result<Value> {
+    Ok(match lit {
+        Lit::Str(lit) => Value::from(lit.value()),
+        Lit::Char(lit) => Value::from(lit.value().to_string()),
+        Lit::Int(lit) => Value::from(lit.base10_parse::<i64>()?),
+        Lit::Float(lit) => Value::from(lit.base10_parse::<f64>()?),
+        Lit::Bool(lit) => Value::from(lit.value),
+        lit => bail!(lit, "Unsupported literal in runtime templates"),
+    })
+}
+
+fn convert_pat(pat: &syn::Pat) -> syn::Result<Pat> {
+    let pats = |pats: &mut dyn Iterator<Item = &syn::Pat>| {
+        pats.map(convert_pat).collect::<syn::Result<Vec<_>>>()
+    };
+    Ok(match pat {
+        syn::Pat::Wild(_) => Pat::Wild,
+        syn::Pat::Ident(ident)
+            if ident.by_ref.is_none() && ident.mutability.is_none() && ident.subpat.is_none() =>
+        {
+            Pat::Bind(ident.ident.to_string())
+        }
+        syn::Pat::Lit(lit) => Pat::Lit(convert_lit(&lit.lit)?),
+        syn::Pat::Tuple(tuple) => Pat::Tuple(pats(&mut tuple.elems.iter())?),
+        syn::Pat::Slice(slice) => Pat::Tuple(pats(&mut slice.elems.iter())?),
+        syn::Pat::Or(or) => Pat::Or(pats(&mut or.cases.iter())?),
+        syn::Pat::Reference(reference) => return convert_pat(&reference.pat),
+        syn::Pat::Paren(paren) => return convert_pat(&paren.pat),
+        pat => bail!(pat, "Unsupported pattern in runtime templates"),
+    })
+}
+
+/// Converts the pattern of `for`, which the template syntax parses as an expression.
+fn convert_expr_pat(expr: &syn::Expr) -> syn::Result<Pat> {
+    Ok(match expr {
+        syn::Expr::Infer(_) => Pat::Wild,
+        syn::Expr::Path(path) if path.qself.is_none() => match path.path.get_ident() {
+            Some(ident) => Pat::Bind(ident.to_string()),
+            None => bail!(path, "Unsupported pattern in runtime templates"),
+        },
+        syn::Expr::Lit(lit) => Pat::Lit(convert_lit(&lit.lit)?),
+        syn::Expr::Tuple(tuple) => Pat::Tuple(
+            tuple
+                .elems
+                .iter()
+                .map(convert_expr_pat)
+                .collect::<syn::Result<_>>()?,
+        ),
+        syn::Expr::Paren(paren) => return convert_expr_pat(&paren.expr),
+        expr => bail!(expr, "Unsupported pattern in runtime templates"),
+    })
+}
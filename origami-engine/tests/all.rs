@@ -1,4 +1,4 @@
-use origami_engine::comp;
+use origami_engine::{comp, comp_file};
 
 #[test]
 fn should_work_with_expr() {
@@ -24,6 +24,7 @@ fn should_be_self_closing() {
     assert_eq!(html.0, "<input/>");
 }
 
+#[cfg(not(feature = "minify_document"))]
 #[test]
 fn should_order_attributes_correctly() {
     comp! {
@@ -35,6 +36,7 @@ fn should_order_attributes_correctly() {
     assert_eq!(html.0, "<div hello abc=\"xyz\"></div>");
 }
 
+#[cfg(not(feature = "minify_document"))]
 #[test]
 fn should_order_attributes_correctly_when_using_placeholder() {
     comp! {
@@ -48,6 +50,7 @@ fn should_order_attributes_correctly_when_using_placeholder() {
     assert_eq!(html.0, "<div hello abc=\"xyz\"></div>");
 }
 
+#[cfg(not(feature = "minify_document"))]
 #[test]
 fn should_work_attribute_with_expression() {
     comp! {
@@ -384,3 +387,1210 @@ fn should_work_with_visibility() {
     let html = foo::foo!();
     assert_eq!(html.0, "<div><div>bar</div></div>");
 }
+
+#[cfg(not(feature = "minify_document"))]
+mod exported {
+    use origami_engine::comp;
+
+    comp! {
+        #[macro_export]
+        pub badge(text) =>
+        span class="badge" {
+            @text;
+        }
+    }
+
+    comp! {
+        #[macro_export]
+        pub card(title) =>
+        div {
+            call crate::exported::badge { text { "new" } }
+            h2 { @title; }
+        }
+    }
+}
+
+#[cfg(not(feature = "minify_document"))]
+#[test]
+fn should_work_with_exported_components() {
+    let html = exported::card!(title { "foo" });
+    assert_eq!(
+        html.0,
+        "<div><span class=\"badge\">new</span><h2>foo</h2></div>"
+    );
+}
+
+#[cfg(not(feature = "minify_document"))]
+#[test]
+fn should_call_exported_components_by_path() {
+    comp! {
+        foo =>
+        div {
+            call exported::badge { text { "bar" } }
+        }
+    }
+    let html = foo!();
+    assert_eq!(html.0, "<div><span class=\"badge\">bar</span></div>");
+}
+
+#[cfg(not(feature = "minify_document"))]
+#[test]
+fn should_work_with_scoped_slots() {
+    struct User {
+        name: &'static str,
+        email: &'static str,
+    }
+    let users = [
+        User {
+            name: "foo",
+            email: "foo@example.com",
+        },
+        User {
+            name: "bar",
+            email: "bar@example.com",
+        },
+    ];
+    comp! {
+        data_table(rows, row) =>
+        table {
+            for (i, item) in @rows;.iter().enumerate(); {
+                tr {
+                    @row(i, item);
+                }
+            }
+        }
+    }
+    let html = data_table!(
+        rows { users },
+        row |i, user| {
+            td { @i.to_string().as_str(); }
+            td { @user.name; }
+            td { @user.email; }
+        }
+    );
+    assert_eq!(
+        html.0,
+        "<table><tr><td>0</td><td>foo</td><td>foo@example.com</td></tr><tr><td>1</td><td>bar</td><td>bar@example.com</td></tr></table>"
+    );
+}
+
+#[test]
+fn should_pass_scoped_slots_through_call() {
+    comp! {
+        list(items, item) =>
+        ul {
+            for value in @items;; {
+                li { @item(value); }
+            }
+        }
+    }
+    comp! {
+        page =>
+        div {
+            call list {
+                items { ["foo", "bar"] },
+                item |value| { "Item: " @value; }
+            }
+        }
+    }
+    let html = page!();
+    assert_eq!(
+        html.0,
+        "<div><ul><li>Item: foo</li><li>Item: bar</li></ul></div>"
+    );
+}
+
+#[test]
+fn should_render_fallback_when_slot_is_omitted_or_empty() {
+    comp! {
+        card(title, footer = { "default footer" }) =>
+        div {
+            h2 { @title; }
+            footer { @footer; }
+        }
+    }
+    let html = card!(title { "foo" });
+    let html2 = card!(title { "foo" }, footer {});
+    let html3 = card!(title { "foo" }, footer { "bar" });
+    assert_eq!(
+        html.0,
+        "<div><h2>foo</h2><footer>default footer</footer></div>"
+    );
+    assert_eq!(html2.0, html.0);
+    assert_eq!(html3.0, "<div><h2>foo</h2><footer>bar</footer></div>");
+}
+
+#[test]
+fn should_check_if_slot_is_passed() {
+    comp! {
+        layout(content, footer = {}) =>
+        main { @content; }
+        if has_slot(footer); {
+            footer { @footer; }
+        }
+    }
+    comp! {
+        page =>
+        call layout { content { "foo" } }
+        call layout { content { "foo" }, footer { "bar" } }
+    }
+    let html = page!();
+    assert_eq!(
+        html.0,
+        "<main>foo</main><main>foo</main><footer>bar</footer>"
+    );
+    let html = layout!(cap => 50, content { "baz" });
+    assert_eq!(html.0, "<main>baz</main>");
+}
+
+#[test]
+fn should_render_component_trait_objects() {
+    use origami_engine::{Buffer, Component};
+
+    struct Heading(&'static str);
+
+    impl Component for Heading {
+        fn render_to(&self, out: &mut Buffer) {
+            out.push_str("<h1>");
+            out.push_str(self.0);
+            out.push_str("</h1>");
+        }
+    }
+
+    comp! {
+        badge(text) =>
+        span { @text; }
+    }
+    let label = String::from("<new>");
+    let widgets: Vec<Box<dyn Component>> = vec![
+        Box::new(Heading("foo")),
+        Box::new(badge!(dyn, text { @label.as_str(); })),
+    ];
+    comp! {
+        page =>
+        div {
+            for widget in &widgets; {
+                @widget;
+            }
+        }
+    }
+    let html = page!();
+    #[cfg(feature = "html_escape")]
+    assert_eq!(html.0, "<div><h1>foo</h1><span>&lt;new&gt;</span></div>");
+    #[cfg(not(feature = "html_escape"))]
+    assert_eq!(html.0, "<div><h1>foo</h1><span><new></span></div>");
+    assert_eq!(widgets[0].render().0, "<h1>foo</h1>");
+}
+
+#[test]
+fn should_render_components_into_a_string() {
+    use origami_engine::anon;
+
+    comp! {
+        badge(text) =>
+        span { @text; }
+        script { "console.log(1)" }
+    }
+    let widget = badge!(text { "bar" });
+    // A `String` has nowhere to keep the CSP sources, so they are dropped.
+    let mut s = String::new();
+    anon! {
+        string s,
+        childrens {
+            call badge { text { "foo" } }
+            @widget;
+        }
+    }
+    assert_eq!(
+        s,
+        "<span>foo</span><script>console.log(1)</script><span>bar</span><script>console.log(1)</script>"
+    );
+}
+
+#[test]
+fn should_render_text_dereferencing_to_str() {
+    struct Name(String);
+
+    impl std::ops::Deref for Name {
+        type Target = str;
+
+        fn deref(&self) -> &str {
+            &self.0
+        }
+    }
+
+    let name = Name("Foo".to_owned());
+    let mut s = String::new();
+    origami_engine::anon! {
+        string s,
+        childrens {
+            b { @name; }
+            b { @&name; }
+        }
+    }
+    assert_eq!(s, "<b>Foo</b><b>Foo</b>");
+}
+
+#[test]
+fn should_pass_context_to_descendants() {
+    struct Locale(&'static str);
+    struct User {
+        name: String,
+    }
+    comp! {
+        greeting =>
+        p {
+            if use_context::<Locale>().is_some_and(|locale| locale.0 == "de"); {
+                "Hallo, "
+            } else {
+                "Hello, "
+            }
+            @use_context::<User>().map(|user| user.name.clone()).unwrap_or_default();
+        }
+    }
+    comp! {
+        sidebar =>
+        aside { call greeting {} }
+    }
+    let locale = "en";
+    comp! {
+        layout =>
+        #provide Locale(locale); {
+            #provide User { name: "foo".to_string() }; {
+                call sidebar {}
+                #provide Locale("de"); {
+                    call greeting {}
+                }
+                call greeting {}
+            }
+        }
+        call greeting {}
+    }
+    let html = layout!();
+    assert_eq!(
+        html.0,
+        "<aside><p>Hello, foo</p></aside><p>Hallo, foo</p><p>Hello, foo</p><p>Hello, </p>"
+    );
+}
+
+#[test]
+fn should_work_with_generic_components() {
+    use std::fmt::Display;
+
+    comp! {
+        select_input<'a, T>(name: &'a str, options: &'a [T], selected: &T, label) where T: Display + PartialEq =>
+        label { @label; }
+        select name=@name; {
+            for option in options; {
+                if option == selected; {
+                    option selected { @option.to_string().as_str(); }
+                } else {
+                    option { @option.to_string().as_str(); }
+                }
+            }
+        }
+    }
+    let numbers = [1, 2, 3];
+    let html = select_input!(
+        name { "number" },
+        options { &numbers },
+        selected { &2 },
+        label { "Number" }
+    );
+    assert_eq!(
+        html.0,
+        "<label>Number</label><select name=\"number\"><option>1</option><option selected>2</option><option>3</option></select>"
+    );
+
+    let suffix = "!";
+    comp! {
+        page =>
+        call select_input {
+            name { "letter" },
+            options { &["a", "b"] },
+            selected { &"a" },
+            label { "Letter" @suffix; }
+        }
+    }
+    let html = page!();
+    assert_eq!(
+        html.0,
+        "<label>Letter!</label><select name=\"letter\"><option selected>a</option><option>b</option></select>"
+    );
+}
+
+#[test]
+fn should_work_with_recursive_components() {
+    struct Comment {
+        text: &'static str,
+        replies: Vec<Comment>,
+    }
+
+    comp! {
+        #[recursive]
+        comment_thread(comment: &Comment, footer) =>
+        li {
+            @comment.text;
+            if !comment.replies.is_empty(); {
+                ul {
+                    for reply in &comment.replies; {
+                        call comment_thread { comment { reply }, footer { "reply" } }
+                    }
+                }
+            }
+            @footer;
+        }
+    }
+
+    let comment = Comment {
+        text: "a",
+        replies: vec![
+            Comment {
+                text: "b",
+                replies: vec![Comment {
+                    text: "c",
+                    replies: vec![],
+                }],
+            },
+            Comment {
+                text: "d",
+                replies: vec![],
+            },
+        ],
+    };
+    let html = comment_thread!(comment { &comment }, footer { "root" });
+    assert_eq!(
+        html.0,
+        "<li>a<ul><li>b<ul><li>creply</li></ul>reply</li><li>dreply</li></ul>root</li>"
+    );
+}
+
+#[test]
+fn should_render_outer_variables_close_to_prop_names() {
+    let items = ["a", "b"];
+    let names = "names";
+    comp! {
+        list(item_list, name) =>
+        ul title=@(names); {
+            @name;
+            for item in items; {
+                li { @item; }
+            }
+            @item_list;
+        }
+    }
+    let html = list!(item_list { li { "c" } }, name { "x" });
+    assert_eq!(
+        html.0,
+        "<ul title=\"names\">x<li>a</li><li>b</li><li>c</li></ul>"
+    );
+
+    let a = "a";
+    comp! {
+        #[origami(unused_props = "allow")]
+        pair(b, unused) =>
+        @a; @b;
+    }
+    assert_eq!(pair!(b { "b" }, unused {}).0, "ab");
+}
+
+#[cfg(not(feature = "minify_document"))]
+#[test]
+fn should_expand_local_templates() {
+    struct Post {
+        title: &'static str,
+        new: bool,
+        tags: [&'static str; 2],
+    }
+
+    comp! {
+        posts(heading) =>
+        def badge(text) {
+            span class="badge" { @text; }
+        }
+        def row(post) {
+            tr {
+                td { @post.title; if post.new; { badge("new") } }
+                td {
+                    for tag in post.tags; {
+                        badge(tag);
+                    }
+                }
+            }
+        }
+        h1 { @heading; }
+        table {
+            for post in &[
+                Post { title: "a", new: true, tags: ["x", "y"] },
+                Post { title: "b", new: false, tags: ["z", "w"] },
+            ]; {
+                row(post)
+            }
+        }
+    }
+    let html = posts!(heading { "Posts" });
+    assert_eq!(
+        html.0,
+        "<h1>Posts</h1><table><tr><td>a<span class=\"badge\">new</span></td><td><span class=\"badge\">x</span><span class=\"badge\">y</span></td></tr><tr><td>b</td><td><span class=\"badge\">z</span><span class=\"badge\">w</span></td></tr></table>"
+    );
+}
+
+#[cfg(not(feature = "minify_document"))]
+#[test]
+fn should_override_blocks_of_extended_components() {
+    comp! {
+        base(content) =>
+        html {
+            head {
+                title { block title { "Site" } }
+                block head {}
+            }
+            body {
+                aside {
+                    block sidebar {
+                        "Links: "
+                        block links { a href="/" { "Home" } }
+                    }
+                }
+                main { @content; }
+            }
+        }
+    }
+    comp! {
+        blog(heading) =>
+        #extends base {
+            block title { "Blog" }
+            block links { a href="/blog" { "Posts" } }
+            block content {
+                h1 { @heading; }
+                block post { p { "No posts" } }
+            }
+        }
+    }
+    comp! {
+        post_page =>
+        #extends blog {
+            block heading { "Post" }
+            block post { p { "Hello" } }
+        }
+    }
+
+    let html = base!(content { "Welcome" });
+    assert_eq!(
+        html.0,
+        "<html><head><title>Site</title></head><body><aside>Links: <a href=\"/\">Home</a></aside><main>Welcome</main></body></html>"
+    );
+
+    let html = blog!(heading { "All posts" });
+    assert_eq!(
+        html.0,
+        "<html><head><title>Blog</title></head><body><aside>Links: <a href=\"/blog\">Posts</a></aside><main><h1>All posts</h1><p>No posts</p></main></body></html>"
+    );
+
+    let html = post_page!();
+    assert_eq!(
+        html.0,
+        "<html><head><title>Blog</title></head><body><aside>Links: <a href=\"/blog\">Posts</a></aside><main><h1>Post</h1><p>Hello</p></main></body></html>"
+    );
+}
+
+#[cfg(not(feature = "minify_document"))]
+#[test]
+fn should_render_portals_into_outlets() {
+    comp! {
+        modal(id, content) =>
+        button value=@id; { "Open" }
+        #portal modals {
+            div id=@id; class="modal" { @content; }
+        }
+    }
+    comp! {
+        page_title(title) =>
+        #portal head { title { @title; } }
+        h1 { @title; }
+    }
+    comp! {
+        layout(content) =>
+        html {
+            head { meta charset="utf-8"; #outlet head; }
+            body {
+                @content;
+                div id="modals" { #outlet modals; }
+            }
+        }
+    }
+    comp! {
+        home =>
+        call layout {
+            content {
+                call page_title { title { "Home" } }
+                call modal { id { "login" }, content { "Log in" } }
+                call modal { id { "signup" }, content { "Sign up" } }
+            }
+        }
+    }
+    let html = home!();
+    assert_eq!(
+        html.0,
+        "<html><head><meta charset=\"utf-8\"/><title>Home</title></head><body><h1>Home</h1><button value=\"login\">Open</button><button value=\"signup\">Open</button><div id=\"modals\"><div id=\"login\" class=\"modal\">Log in</div><div id=\"signup\" class=\"modal\">Sign up</div></div></body></html>"
+    );
+
+    comp! {
+        elements =>
+        portal src="a" { outlet name="b"; }
+        include;
+    }
+    assert_eq!(
+        elements!().0,
+        "<portal src=\"a\"><outlet name=\"b\"/></portal><include/>"
+    );
+}
+
+#[cfg(not(feature = "minify_document"))]
+#[test]
+fn should_render_once_blocks_once_per_render() {
+    comp! {
+        badge(text) =>
+        style once(head) { ".badge { color: red; }" }
+        script once { "let badges = 0;" }
+        span class="badge" { @text; }
+    }
+    comp! {
+        page =>
+        html {
+            head { #outlet head; }
+            body {
+                for text in ["a", "b", "c"]; {
+                    call badge { text { @text; } }
+                }
+            }
+        }
+    }
+    let html = page!();
+    assert_eq!(
+        html.0,
+        "<html><head><style>.badge { color: red; }</style></head><body><script>let badges = 0;</script><span class=\"badge\">a</span><span class=\"badge\">b</span><span class=\"badge\">c</span></body></html>"
+    );
+
+    // Each render starts over.
+    let html = badge!(text { "d" });
+    assert_eq!(
+        html.0,
+        "<script>let badges = 0;</script><span class=\"badge\">d</span>"
+    );
+
+    // Blocks are the same if they render the same, however their literals are written.
+    comp! {
+        counter =>
+        script once { r"let badges = 0;" }
+        script once { "let counters = 0;" }
+    }
+    comp! {
+        widgets =>
+        call badge { text { "e" } }
+        call counter {}
+    }
+    assert_eq!(
+        widgets!().0,
+        "<script>let badges = 0;</script><span class=\"badge\">e</span><script>let counters = 0;</script>"
+    );
+}
+
+#[cfg(not(feature = "minify_document"))]
+#[test]
+fn should_scope_styles_to_components() {
+    comp! {
+        card(title, content) =>
+        style scoped {
+            ".card, .card > h2:hover { color: red; } a::before { content: \"{\"; } @media (max-width: 600px) { .card { color: blue; } } @keyframes spin { from { opacity: 0; } }"
+        }
+        div class="card" {
+            h2 { @title; }
+            @content;
+        }
+    }
+    comp! {
+        page =>
+        call card { title { "Title" }, content { p { "Text" } } }
+    }
+    let html = page!();
+    let start = html.0.find("data-o-").unwrap();
+    let scope = &html.0[start..start + "data-o-".len() + 8];
+    let style = r#".card[scope], .card > h2[scope]:hover { color: red; } a[scope]::before { content: "{"; } @media (max-width: 600px) { .card[scope] { color: blue; } } @keyframes spin { from { opacity: 0; } }"#
+        .replace("scope", scope);
+    assert_eq!(
+        html.0,
+        format!(
+            r#"<style>{}</style><div class="card" {}><h2 {}>Title</h2><p>Text</p></div>"#,
+            style, scope, scope
+        )
+    );
+}
+
+#[cfg(not(feature = "minify_document"))]
+#[test]
+fn should_include_files() {
+    comp! {
+        page =>
+        style src_file="tests/assets/card.css";
+        script src_file="tests/assets/menu.js" {}
+        div class="card" { "Card" }
+        #include "tests/assets/footer.html";
+    }
+    let html = page!();
+    #[cfg(feature = "minify_html")]
+    assert_eq!(
+        html.0,
+        "<style>.card { color: red; }</style><script>console.log(\"menu\");</script><div class=\"card\">Card</div><footer><p>Footer</footer>"
+    );
+    #[cfg(not(feature = "minify_html"))]
+    assert_eq!(
+        html.0,
+        "<style>.card { color: red; }\n</style><script>console.log(\"menu\");\n</script><div class=\"card\">Card</div><footer>\n    <p>Footer</p>\n</footer>\n"
+    );
+}
+
+#[cfg(feature = "minify_html")]
+#[test]
+fn should_minify_with_block_options() {
+    comp! {
+        page =>
+        #include minify(keep_comments) "tests/assets/comments.html";
+        #include "tests/assets/comments.html";
+        #include minify(keep_closing_tags) "tests/assets/footer.html";
+    }
+    assert_eq!(
+        page!().0,
+        "<!--[if IE]><p>Old browser</p><![endif]--><p>Hello<p>Hello<footer><p>Footer</p></footer>"
+    );
+}
+
+#[cfg(not(feature = "minify_document"))]
+#[test]
+fn should_apply_component_settings() {
+    let titel = "Local";
+    comp! {
+        #[origami(self_closing = "html", unused_props = "allow", similar_props = "allow")]
+        page(title, footer) =>
+        h1 { @titel; }
+        br;
+        input value="x";
+    }
+    assert_eq!(
+        page!(title { "Hello" }, footer {}).0,
+        "<h1>Local</h1><br><input value=\"x\">"
+    );
+}
+
+#[cfg(feature = "html_escape")]
+#[test]
+fn should_apply_component_escape_setting() {
+    let markup = "<b>Bold</b>";
+    comp! {
+        #[origami(escape = false)]
+        raw =>
+        p { @markup; }
+    }
+    assert_eq!(raw!().0, "<p><b>Bold</b></p>");
+}
+
+#[cfg(feature = "minify_html")]
+#[test]
+fn should_apply_component_minify_setting() {
+    comp! {
+        #[origami(minify = false)]
+        page =>
+        #include "tests/assets/comments.html";
+        #include minify() "tests/assets/comments.html";
+    }
+    assert_eq!(
+        page!().0,
+        "<!--[if IE]><p>Old browser</p><![endif]-->\n<p>Hello</p>\n<p>Hello"
+    );
+}
+
+#[test]
+fn should_load_component_from_template_file() {
+    let items = ["One", "Two"];
+    comp_file!(page(title, body) => "tests/assets/page.origami");
+    let html = page!(title { "Home" } body { div { "Content" } });
+    assert_eq!(
+        html.0,
+        "<html><head><title>Home</title></head><body><h1>Home</h1><div>Content</div><p>One</p><p>Two</p></body></html>"
+    );
+}
+
+#[test]
+fn should_load_component_from_template_file_with_prop_in_expression() {
+    comp_file!(list(rows) => "tests/assets/list.origami");
+    let rows = ["One", "Two"];
+    let html = list!(rows { rows.iter() });
+    assert_eq!(html.0, "<ul><li>One</li><li>Two</li></ul>");
+}
+
+// The runtime does not minify, so it renders like `comp!` without `minify_document`.
+#[cfg(all(
+    feature = "runtime",
+    feature = "html_escape",
+    not(feature = "minify_document")
+))]
+#[test]
+fn should_render_runtime_template_like_comp() {
+    use origami_engine::runtime::Template;
+
+    let title = "<Posts>";
+    let posts = [("First", 3), ("Second", 0)];
+    comp! {
+        page =>
+        h1 class="title" { @title; }
+        ul {
+            for (name, comments) in posts.iter(); {
+                li {
+                    @name;
+                    match *comments; {
+                        0 => { " (no comments)" },
+                        n => { " (" @n.to_string().as_str(); ")" },
+                    }
+                }
+            }
+        }
+        if posts.is_empty(); { p { "None" } } else { p noescape { "<b>Done</b>" } }
+    }
+    let template = Template::parse(
+        r#"
+        h1 class="title" { @title; }
+        ul {
+            for (name, comments) in posts.iter(); {
+                li {
+                    @name;
+                    match comments; {
+                        0 => { " (no comments)" },
+                        n => { " (" @n; ")" },
+                    }
+                }
+            }
+        }
+        if posts.is_empty(); { p { "None" } } else { p noescape { "<b>Done</b>" } }
+        "#,
+    )
+    .unwrap();
+    let html = template
+        .render(&serde_json::json!({ "title": title, "posts": posts }))
+        .unwrap();
+    assert_eq!(html.0, page!().0);
+}
+
+#[cfg(all(feature = "runtime", feature = "html_escape"))]
+#[test]
+fn should_report_runtime_template_errors() {
+    use origami_engine::runtime::{Template, Templates};
+
+    let error = Template::parse("div {\n    p { @std::process::exit(1); }\n}").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "2:10: Unsupported expression in runtime templates"
+    );
+
+    let templates = Templates::new();
+    templates
+        .insert("greeting", r#"p { "Hello, " @user.name; }"#)
+        .unwrap();
+    let html = templates
+        .render(
+            "greeting",
+            &serde_json::json!({ "user": { "name": "<Ann>" } }),
+        )
+        .unwrap();
+    assert_eq!(html.0, "<p>Hello, &lt;Ann&gt;</p>");
+    let error = templates
+        .render("greeting", &serde_json::json!({}))
+        .unwrap_err();
+    assert_eq!(error.to_string(), "1:16: Unknown variable `user`");
+    assert!(templates.render("missing", &()).is_err());
+
+    // Attribute values can't be ended by data.
+    let template = Template::parse(r#"a href=@url; title="a \"b\" & c" { "Link" }"#).unwrap();
+    assert_eq!(
+        template
+            .render(&serde_json::json!({ "url": "\" onclick=\"alert(1)" }))
+            .unwrap()
+            .0,
+        "<a href=\"&quot; onclick=&quot;alert(1)\" title=\"a &quot;b&quot; &amp; c\">Link</a>"
+    );
+
+    // Untrusted templates can't exhaust memory or overflow.
+    let template = Template::parse("for i in 0..n; { @i; }").unwrap();
+    assert_eq!(
+        template.render(&serde_json::json!({ "n": 3 })).unwrap().0,
+        "012"
+    );
+    let error = template
+        .render(&serde_json::json!({ "n": 10_000_000_000_i64 }))
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "1:10: Range of 10000000000 items is longer than the limit of 100000"
+    );
+    let template = Template::parse("for i in 3..=n; { @i; } @-n;").unwrap();
+    assert_eq!(
+        template.render(&serde_json::json!({ "n": 1 })).unwrap().0,
+        "-1"
+    );
+    let error = template
+        .render(&serde_json::json!({ "n": i64::MIN }))
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "1:26: Cannot negate -9223372036854775808, it overflows"
+    );
+}
+
+#[cfg(feature = "hot_reload")]
+#[test]
+fn should_render_changed_template_file_with_hot_reload() {
+    struct Restore(&'static str, String);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            std::fs::write(self.0, &self.1).unwrap();
+        }
+    }
+
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/assets/hot.origami");
+    let items = vec!["One".to_string(), "Two".to_string()];
+    comp_file!(hot(title) => "tests/assets/hot.origami");
+    let _restore = Restore(path, std::fs::read_to_string(path).unwrap());
+    // Props are only rendered for the reloaded template when the file changed.
+    let count = std::cell::Cell::new(0);
+    let counted = || {
+        count.set(count.get() + 1);
+        "Home"
+    };
+    assert_eq!(
+        hot!(title { @counted(); }).0,
+        "<div><h1>Home</h1><p>One</p><p>Two</p></div>"
+    );
+    assert_eq!(count.get(), 1);
+    std::fs::write(
+        path,
+        "section { h2 { @title; } ul { for item in items.iter(); { li { @item; } } } }",
+    )
+    .unwrap();
+    assert_eq!(
+        hot!(title { @counted(); }).0,
+        "<section><h2>Home</h2><ul><li>One</li><li>Two</li></ul></section>"
+    );
+    assert_eq!(count.get(), 2);
+    // Templates the runtime cannot render fall back to the compiled version.
+    std::fs::write(path, "section { @items.first(); }").unwrap();
+    assert_eq!(
+        hot!(title { "Home" }).0,
+        "<div><h1>Home</h1><p>One</p><p>Two</p></div>"
+    );
+}
+
+#[cfg(feature = "live_reload")]
+#[tokio::test]
+async fn should_inject_live_reload_script_and_signal_changes() {
+    use std::net::SocketAddr;
+    use std::time::Duration;
+
+    use axum::routing::get;
+    use origami_engine::csp::CspLayer;
+    use origami_engine::live_reload::LiveReloadLayer;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    async fn request(addr: SocketAddr, path: &str) -> TcpStream {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            path
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        stream
+    }
+
+    async fn read_until(stream: &mut TcpStream, pattern: &str) -> String {
+        let mut response = Vec::new();
+        let mut buf = [0; 1024];
+        while !String::from_utf8_lossy(&response).contains(pattern) {
+            let n = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buf))
+                .await
+                .expect("timed out")
+                .unwrap();
+            assert!(n > 0, "connection closed");
+            response.extend_from_slice(&buf[..n]);
+        }
+        String::from_utf8(response).unwrap()
+    }
+
+    let dir = std::env::temp_dir().join(format!("origami-live-reload-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("page.origami"), "p { \"Old\" }").unwrap();
+
+    comp! {
+        page =>
+        html { body { "Hello" } }
+    }
+    let app = axum::Router::new()
+        .route("/", get(|| async { page!() }))
+        .route("/text", get(|| async { "<body></body>" }))
+        .layer(CspLayer::new("default-src 'self'"))
+        .layer(
+            LiveReloadLayer::new()
+                .watch(&dir)
+                .interval(Duration::from_millis(10)),
+        );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let page = read_until(&mut request(addr, "/").await, "</html>").await;
+    // The script is allowed by its hash.
+    assert!(page.contains(
+        "content-security-policy: default-src 'self'; script-src 'self' 'sha256-cMLWhDYT6oSBuna0dbh40Sh0uKnPLMtc0FdJOSyQx/U='\r\n"
+    ));
+    assert!(page.ends_with(
+        r#"<html><body>Hello<script>new EventSource("/__origami/live-reload").addEventListener("reload",()=>location.reload())</script></body></html>"#
+    ));
+    let text = read_until(&mut request(addr, "/text").await, "</body>").await;
+    assert!(text.ends_with("<body></body>"));
+
+    let mut events = request(addr, "/__origami/live-reload").await;
+    read_until(&mut events, "text/event-stream").await;
+    std::fs::write(dir.join("page.origami"), "p { \"New\" }\n").unwrap();
+    read_until(&mut events, "event: reload").await;
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn should_collect_csp_hashes_of_inline_blocks() {
+    use origami_engine::csp::Directive;
+    use origami_engine::Component;
+
+    comp! {
+        widget =>
+        script { "console.log(1)" }
+    }
+    comp! {
+        page =>
+        style { "p{color:red}" }
+        call widget {}
+        call widget {}
+    }
+    let html = page!(dyn).rendered();
+    assert_eq!(
+        html.csp.sources(Directive::Script),
+        ["'sha256-CihokcEcBW4atb/CW/XWsvWwbTjqwQlE9nj9ii5ww5M='"]
+    );
+    assert_eq!(
+        html.csp.sources(Directive::Style),
+        ["'sha256-p0bF+un5yUb9MBO6xRb8kPHlY2BdpHVtLiFkDrZPF64='"]
+    );
+    assert_eq!(html.csp.to_string(), "script-src 'sha256-CihokcEcBW4atb/CW/XWsvWwbTjqwQlE9nj9ii5ww5M='; style-src 'sha256-p0bF+un5yUb9MBO6xRb8kPHlY2BdpHVtLiFkDrZPF64='");
+    assert_eq!(
+        html.csp.merge_into("default-src 'self'; img-src *; style-src 'unsafe-inline'"),
+        "default-src 'self'; img-src *; style-src 'unsafe-inline'; script-src 'self' 'sha256-CihokcEcBW4atb/CW/XWsvWwbTjqwQlE9nj9ii5ww5M='"
+    );
+    // Hashes would disable `'unsafe-inline'`, so those directives are left untouched.
+    assert_eq!(
+        html.csp.merge_into("default-src 'self' 'unsafe-inline'"),
+        "default-src 'self' 'unsafe-inline'"
+    );
+    // Hashes of a component only cover its own blocks.
+    assert!(page!(csp).sources(Directive::Script).is_empty());
+    assert_eq!(
+        widget!(csp).sources(Directive::Script),
+        ["'sha256-CihokcEcBW4atb/CW/XWsvWwbTjqwQlE9nj9ii5ww5M='"]
+    );
+}
+
+#[test]
+fn should_collect_csp_hashes_of_component_with_prop_in_expression() {
+    use origami_engine::csp::Directive;
+
+    comp! {
+        data_table(rows) =>
+        ul {
+            for item in @rows;; {
+                li { @item; }
+            }
+        }
+        script { "console.log(1)" }
+    }
+    assert_eq!(
+        data_table!(csp).sources(Directive::Script),
+        ["'sha256-CihokcEcBW4atb/CW/XWsvWwbTjqwQlE9nj9ii5ww5M='"]
+    );
+}
+
+#[test]
+fn should_render_nonce_from_context() {
+    use origami_engine::csp::{Directive, Nonce};
+    use origami_engine::Component;
+
+    comp! {
+        page =>
+        #provide Nonce::new("r4nd0m"); {
+            script nonce { "console.log(1)" }
+        }
+        style nonce { "p{color:red}" }
+    }
+    let html = page!(dyn).rendered();
+    assert_eq!(
+        html.html.0,
+        "<script nonce=\"r4nd0m\">console.log(1)</script><style>p{color:red}</style>"
+    );
+    assert_eq!(html.csp.sources(Directive::Script), ["'nonce-r4nd0m'"]);
+    // Without a nonce, the block is allowed by its hash.
+    assert_eq!(
+        html.csp.sources(Directive::Style),
+        ["'sha256-p0bF+un5yUb9MBO6xRb8kPHlY2BdpHVtLiFkDrZPF64='"]
+    );
+}
+
+#[cfg(feature = "axum")]
+#[tokio::test]
+async fn should_add_csp_sources_to_header() {
+    use axum::routing::get;
+    use origami_engine::csp::{CspLayer, Nonce};
+    use origami_engine::Component;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    comp! {
+        page =>
+        html { body { script { "console.log(1)" } } }
+    }
+    comp! {
+        invalid_nonce =>
+        #provide Nonce::new("r4nd\n0m"); {
+            script nonce { "console.log(1)" }
+        }
+    }
+    let app = axum::Router::new()
+        .route("/", get(|| async { page!(dyn).rendered() }))
+        .route(
+            "/invalid-nonce",
+            get(|| async { invalid_nonce!(dyn).rendered() }),
+        )
+        .layer(CspLayer::new("default-src 'self'"));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let get = |path: &str| {
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            path
+        );
+        async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(request.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        }
+    };
+    assert!(get("/").await.contains(
+        "content-security-policy: default-src 'self'; script-src 'self' 'sha256-CihokcEcBW4atb/CW/XWsvWwbTjqwQlE9nj9ii5ww5M='\r\n"
+    ));
+    // A nonce that cannot be sent leaves the policy of the application, never no policy.
+    assert!(get("/invalid-nonce")
+        .await
+        .contains("content-security-policy: default-src 'self'\r\n"));
+}
+
+#[cfg(not(feature = "minify_document"))]
+#[test]
+fn should_pretty_print_block_elements() {
+    comp! {
+        page =>
+        html {
+            head {
+                title { "Home" }
+                style { "p { color: red; }" }
+            }
+            body {
+                div class="list" {
+                    ul {
+                        li { "Øne" }
+                        li { "Two " b { "bold" } }
+                    }
+                    pre { "  keep\n  this" }
+                    div {}
+                }
+                p { "Text " span { "inline" } }
+                form { textarea { "a" } }
+            }
+        }
+    }
+    assert_eq!(
+        page!().pretty().0,
+        r#"<html>
+  <head>
+    <title>Home</title>
+    <style>p { color: red; }</style>
+  </head>
+  <body>
+    <div class="list">
+      <ul>
+        <li>Øne</li>
+        <li>Two <b>bold</b></li>
+      </ul>
+      <pre>  keep
+  this</pre>
+      <div></div>
+    </div>
+    <p>Text <span>inline</span></p>
+    <form><textarea>a</textarea></form>
+  </body>
+</html>"#
+    );
+}
+
+#[test]
+fn should_not_pretty_print_inline_content() {
+    comp! {
+        page =>
+        "Hello " span { "world" } div { p { "!" } }
+    }
+    let html = page!();
+    assert_eq!(html.pretty().0, html.0);
+}
+
+#[cfg(feature = "minify_document")]
+#[test]
+fn should_minify_static_markup() {
+    let name = "a  <b>\n  c";
+    let class = "x  y";
+    comp! {
+        page =>
+        div class="card" id=@class; {
+            "Hello,
+            world"
+            @name;!
+            ul {
+                li { "One" }
+                li { "Two" }
+            }
+            p style="color: red; margin: 0px" onclick="alert( 'hi' )" { "Text" }
+            div {}
+            pre { "  keep\n  this" }
+            img src="a.png" alt="A picture";
+            input value="x";
+        }
+    }
+    assert_eq!(
+        page!().0,
+        concat!(
+            r#"<div class=card id="x  y">Hello, worlda  <b>"#,
+            "\n  c",
+            r#"<ul><li>One<li>Two</li></ul>"#,
+            r#"<p style=color:red;margin:0 onclick="alert(`hi`)">Text<div></div>"#,
+            "<pre>  keep\n  this</pre>",
+            r#"<img src=a.png alt="A picture"/><input value="x"/></div>"#,
+        )
+    );
+}
@@ -2,6 +2,12 @@
 fn try_all() {
     let t = trybuild::TestCases::new();
     t.pass("tests/trybuild/pass/*.rs");
+    t.compile_fail("tests/trybuild/fail/should_fail_when_prop_*.rs");
+    t.compile_fail("tests/trybuild/fail/should_fail_when_local_template_*.rs");
+    t.compile_fail("tests/trybuild/fail/should_fail_when_block_*.rs");
+    t.compile_fail("tests/trybuild/fail/should_fail_when_template_file_*.rs");
+    t.compile_fail("tests/trybuild/fail/should_fail_when_component_setting_*.rs");
+    t.compile_fail("tests/trybuild/fail/should_fail_when_string_output_*.rs");
 
     #[cfg(feature = "html_escape")]
     t.compile_fail("tests/trybuild/fail/should_fail_when_html_escape_is_enabled_*.rs");
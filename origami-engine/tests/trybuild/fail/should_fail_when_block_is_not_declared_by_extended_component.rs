@@ -0,0 +1,14 @@
+use origami_engine::comp;
+fn main() {
+    comp! {
+        base =>
+        title { block title { "Site" } }
+    }
+    comp! {
+        foo =>
+        #extends base {
+            block titel { "Foo" }
+        }
+    }
+    foo!();
+}
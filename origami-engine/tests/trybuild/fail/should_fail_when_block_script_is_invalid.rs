@@ -0,0 +1,9 @@
+use origami_engine::comp;
+fn main() {
+    comp! {
+        foo =>
+        script { "const menu = document.querySelector(\"nav\");
+menu.addEventListener(\"click\", () => {);" }
+    }
+    foo!();
+}
@@ -0,0 +1,8 @@
+use origami_engine::comp;
+fn main() {
+    comp! {
+        foo =>
+        style src_file="../../../../origami-engine/tests/assets/broken.css";
+    }
+    foo!();
+}
@@ -0,0 +1,11 @@
+use origami_engine::comp;
+fn main() {
+    comp! {
+        foo =>
+        style { ".card {
+    color: red;
+}
+.title { color: blue; } }" }
+    }
+    foo!();
+}
@@ -0,0 +1,9 @@
+use origami_engine::comp;
+fn main() {
+    comp! {
+        #[origami(escpae = false)]
+        foo =>
+        p { "Hello" }
+    }
+    foo!();
+}
@@ -0,0 +1,11 @@
+use origami_engine::comp;
+fn main() {
+    comp! {
+        foo =>
+        def badge(text) {
+            span { @text; badge(text) }
+        }
+        badge("new")
+    }
+    foo!();
+}
@@ -0,0 +1,11 @@
+use origami_engine::comp;
+fn main() {
+    comp! {
+        foo =>
+        def badge(text, class) {
+            span class=@class; { @text; }
+        }
+        badge("new")
+    }
+    foo!();
+}
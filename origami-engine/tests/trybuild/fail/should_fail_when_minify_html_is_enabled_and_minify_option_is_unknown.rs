@@ -0,0 +1,11 @@
+use origami_engine::comp;
+
+fn main() {
+    comp! {
+        foo =>
+        script minify(keep_everything) {
+            "console.log(1);"
+        }
+    }
+    foo!();
+}
@@ -0,0 +1,9 @@
+use origami_engine::comp;
+fn main() {
+    comp! {
+        foo =>
+        script nominify { r#"let menu = document.querySelector("nav");
+if (menu { menu.remove(); }"# }
+    }
+    foo!();
+}
@@ -0,0 +1,9 @@
+use origami_engine::comp;
+fn main() {
+    comp! {
+        #[origami(unused_props = "deny")]
+        foo(title, body) =>
+        h1 { @title; }
+    }
+    foo!(title { "Hello" }, body { "World" });
+}
@@ -0,0 +1,11 @@
+#![deny(warnings)]
+use origami_engine::comp;
+fn main() {
+    let titel = "Local";
+    comp! {
+        foo(title, body) =>
+        h1 { @title; }
+        p { @titel; }
+    }
+    let _ = foo!(title { "Hello" }, body { "World" });
+}
@@ -0,0 +1,11 @@
+use origami_engine::comp;
+fn main() {
+    let titel = "Local";
+    comp! {
+        #[origami(similar_props = "deny")]
+        foo(title) =>
+        h1 { @title; }
+        p { @titel; }
+    }
+    foo!(title { "Hello" });
+}
@@ -0,0 +1,12 @@
+use origami_engine::anon;
+fn main() {
+    let mut s = String::new();
+    anon! {
+        string s,
+        childrens {
+            #provide 1u8; {
+                p { "Hello" }
+            }
+        }
+    }
+}
@@ -0,0 +1,5 @@
+use origami_engine::comp_file;
+fn main() {
+    comp_file!(broken => "../../../../origami-engine/tests/assets/broken.origami");
+    broken!();
+}
@@ -0,0 +1,38 @@
+use syn::visit_mut::{self, VisitMut};
+use syn::{parse_quote, Expr};
+
+/// Rewrites `use_context::<T>()` calls into lookups on the render buffer.
+pub(super) struct UseContext<'a> {
+    pub(super) s: &'a Expr,
+}
+
+impl UseContext<'_> {
+    pub(super) fn rewrite(&mut self, expr: &Expr) -> Expr {
+        let mut expr = expr.clone();
+        self.visit_expr_mut(&mut expr);
+        expr
+    }
+}
+
+impl VisitMut for UseContext<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        if let Expr::Call(call) = expr {
+            if let Expr::Path(path) = &*call.func {
+                if path.qself.is_none()
+                    && path.path.leading_colon.is_none()
+                    && path.path.segments.len() == 1
+                    && path.path.segments[0].ident == "use_context"
+                    && call.args.is_empty()
+                {
+                    let s = self.s;
+                    let args = &path.path.segments[0].arguments;
+                    *expr = parse_quote! {
+                        ::origami_engine::__private::AsBuffer::as_buffer(&#s).use_context #args ()
+                    };
+                    return;
+                }
+            }
+        }
+        visit_mut::visit_expr_mut(self, expr);
+    }
+}
@@ -0,0 +1,32 @@
+//! Hashes of inline blocks for Content Security Policies, computed at compile time.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+
+/// Source expression allowing an inline block with `content`, `'sha256-<base64>'`.
+pub fn source(content: &str) -> String {
+    format!("'sha256-{}'", STANDARD.encode(sha256(content)))
+}
+
+/// SHA-256 digest of `content`.
+pub fn sha256(content: &str) -> [u8; 32] {
+    Sha256::digest(content.as_bytes()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::source;
+
+    #[test]
+    fn hash_sources() {
+        assert_eq!(
+            source(""),
+            "'sha256-47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU='"
+        );
+        assert_eq!(
+            source("abc"),
+            "'sha256-ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0='"
+        );
+    }
+}
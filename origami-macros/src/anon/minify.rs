@@ -0,0 +1,134 @@
+//! Minification of the static markup, enabled by the `minify_document` feature.
+//!
+//! Only literals written in the template are changed, the output of expressions is never
+//! touched.
+
+use minify_html::Cfg;
+
+/// What follows an element in its parent, deciding whether its closing tag can be omitted.
+pub enum Following {
+    /// Another element, with its tag.
+    Element(String),
+    /// Anything else, or something only known when rendering, e.g. a component, a block, or the
+    /// end of the parent, which may be followed by markup spliced in from props.
+    Unknown,
+}
+
+/// Elements whose content is rendered with its whitespace.
+pub fn is_preformatted(tag: &str) -> bool {
+    matches!(tag, "pre" | "textarea")
+}
+
+/// Collapses each run of whitespace into a single space, which renders the same.
+pub fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut whitespace = false;
+    for c in text.chars() {
+        if matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0C') {
+            if !whitespace {
+                collapsed.push(' ');
+            }
+            whitespace = true;
+        } else {
+            collapsed.push(c);
+            whitespace = false;
+        }
+    }
+    collapsed
+}
+
+/// Minifies the content of `style` and event handler attributes.
+///
+/// Values with character references are kept as they are, the minifiers would read them as code.
+pub fn attribute_value(name: &str, value: &str) -> String {
+    let name = name.to_ascii_lowercase();
+    let minified = if value.contains('&') {
+        None
+    } else if name == "style" && !value.contains(['{', '}']) {
+        minify_block("<style>*{", value, "}</style>", |cfg| cfg.minify_css = true)
+    } else if name.starts_with("on") {
+        minify_block("<script>", value, "</script>", |cfg| cfg.minify_js = true)
+    } else {
+        None
+    };
+    // The minifiers may change the quotes of strings.
+    match minified.map(|minified| minified.replace('"', "&quot;")) {
+        Some(minified) if minified.len() < value.len() => minified,
+        _ => value.to_owned(),
+    }
+}
+
+/// Minifies `value` wrapped in a block, returning it without the wrapper.
+fn minify_block(start: &str, value: &str, end: &str, configure: fn(&mut Cfg)) -> Option<String> {
+    let mut cfg = Cfg::default();
+    configure(&mut cfg);
+    let minified = minify_html::minify(format!("{}{}{}", start, value, end).as_bytes(), &cfg);
+    let minified = String::from_utf8(minified).ok()?;
+    Some(minified.strip_prefix(start)?.strip_suffix(end)?.to_owned())
+}
+
+/// Writes an attribute value, without quotes when it is safe.
+///
+/// Values of the last attribute of a self-closing tag keep their quotes, the `/` would be read
+/// as part of them.
+pub fn quote(value: &str, last_self_closing: bool) -> String {
+    let unquoted = !value.is_empty()
+        && !last_self_closing
+        && !value.contains([' ', '\t', '\n', '\r', '\x0C', '"', '\'', '=', '<', '>', '`']);
+    if unquoted {
+        format!("={}", value)
+    } else {
+        format!("=\"{}\"", value)
+    }
+}
+
+/// Whether the closing tag of `tag` is optional, see
+/// <https://html.spec.whatwg.org/multipage/syntax.html#optional-tags>.
+///
+/// Only the next sibling is considered, and only when it is a static element.
+pub fn is_end_tag_optional(tag: &str, following: &Following) -> bool {
+    let Following::Element(next) = following else {
+        return false;
+    };
+    match tag {
+        "li" => next == "li",
+        "dt" | "dd" => matches!(next.as_str(), "dt" | "dd"),
+        "option" => matches!(next.as_str(), "option" | "optgroup"),
+        "tr" => next == "tr",
+        "td" | "th" => matches!(next.as_str(), "td" | "th"),
+        "p" => matches!(
+            next.as_str(),
+            "address"
+                | "article"
+                | "aside"
+                | "blockquote"
+                | "details"
+                | "div"
+                | "dl"
+                | "fieldset"
+                | "figcaption"
+                | "figure"
+                | "footer"
+                | "form"
+                | "h1"
+                | "h2"
+                | "h3"
+                | "h4"
+                | "h5"
+                | "h6"
+                | "header"
+                | "hgroup"
+                | "hr"
+                | "main"
+                | "menu"
+                | "nav"
+                | "ol"
+                | "p"
+                | "pre"
+                | "section"
+                | "table"
+                | "ul"
+        ),
+        _ => false,
+    }
+}
@@ -7,22 +7,35 @@ use syn::parse::Parse;
 use syn::spanned::Spanned;
 use syn::{braced, parse_quote, Expr, Ident, LitStr, Path, Token};
 
-mod children;
-
-use crate::anon::children::CustomMatchArm;
-use crate::utils::kw::{childrens, concat_args, concat_args_ident, string};
 #[cfg(feature = "html_escape")]
-use crate::utils::kw::{escape, noescape};
+use origami_syntax::kw::{escape, noescape};
+use origami_syntax::visit::{self, Visit};
+#[cfg(feature = "minify_html")]
+use origami_syntax::MinifyOptions;
+use origami_syntax::{
+    parse_childrens, AttributeKey, AttributeValue, Attributes, Children, Childrens, Context,
+    CustomMatchArm, HtmlChildrens, Once,
+};
+
+mod context;
+pub mod csp;
+#[cfg(feature = "minify_document")]
+mod minify;
+
+use crate::config::Config;
+use crate::utils::kw::{childrens, concat_args, concat_args_ident, config, string};
 use crate::utils::{bail, combine_to_lit};
 
-use self::children::attributes::AttributeValue;
-use self::children::{AttributeKey, Attributes, Children, Childrens, Context, HtmlChildrens};
+use self::context::UseContext;
+#[cfg(feature = "minify_document")]
+use self::minify::Following;
 
 pub struct Anon {
     expr: Expr,
     childrens: Childrens,
     concat_args: Option<TokenStream>,
     concat_args_return_ident: Option<Ident>,
+    config: Config,
 }
 
 impl Parse for Anon {
@@ -32,6 +45,7 @@ impl Parse for Anon {
             concat_args: None,
             childrens: Vec::new(),
             expr: parse_quote!(s),
+            config: Config::load()?,
         };
         let mut count = 0;
         while !input.is_empty() {
@@ -67,12 +81,25 @@ impl Parse for Anon {
                 count += 1;
                 continue;
             }
+            if input.peek(config) {
+                if !anon.childrens.is_empty() {
+                    bail!(input, "`config` must come before `childrens`");
+                }
+                input.parse::<config>()?;
+                let content;
+                braced!(content in input);
+                anon.config.apply_overrides(content.parse()?)?;
+                count += 1;
+                continue;
+            }
             if input.peek(childrens) {
                 if !anon.childrens.is_empty() {
                     bail!(input, "duplicate `childrens`");
                 }
                 input.parse::<childrens>()?;
                 let mut ctx = Context {
+                    scope: None,
+                    read_files: true,
                     #[cfg(feature = "html_escape")]
                     escape: if input.peek(escape) {
                         input.parse::<escape>()?;
@@ -81,14 +108,14 @@ impl Parse for Anon {
                         input.parse::<noescape>()?;
                         false
                     } else {
-                        true
+                        anon.config.escape
                     },
+                    #[cfg(feature = "minify_html")]
+                    minify: anon.config.minify,
                 };
                 let content;
                 braced!(content in input);
-                while !content.is_empty() {
-                    anon.childrens.push(Children::parse(&content, &mut ctx)?);
-                }
+                anon.childrens = parse_childrens(&content, &mut ctx)?;
                 count += 1;
                 continue;
             }
@@ -98,6 +125,25 @@ impl Parse for Anon {
     }
 }
 
+impl Anon {
+    /// Builds the [`Csp`](https://docs.rs/origami-engine/latest/origami_engine/csp/struct.Csp.html)
+    /// with the hashes of the `script` and `style` blocks of the template.
+    pub fn csp(&self) -> TokenStream {
+        let mut sources = CspSources {
+            config: &self.config,
+            ts: TokenStream::new(),
+        };
+        sources.visit_childrens(&self.childrens);
+        let ts = sources.ts;
+        quote! {{
+            #[allow(unused_mut)]
+            let mut csp = ::origami_engine::csp::Csp::new();
+            #ts
+            csp
+        }}
+    }
+}
+
 impl ToTokens for Anon {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         let mut concat_args = self.concat_args.clone().unwrap_or_default();
@@ -105,7 +151,11 @@ impl ToTokens for Anon {
             concat_args: &mut concat_args,
             ts: tokens,
             s: &self.expr,
+            config: &self.config,
+            #[cfg(feature = "minify_document")]
+            preformatted: false,
         };
+        extend.ts.extend(self.config.dependency());
         extend.extend_childrens(&self.childrens, false);
         if let Some(ident) = &self.concat_args_return_ident {
             tokens.extend(quote! {
@@ -125,11 +175,12 @@ impl ToTokens for Anon {
 enum Minify {
     Script,
     Style,
+    Html,
 }
 
 enum ProcessType {
     #[cfg(feature = "minify_html")]
-    Minify(Minify),
+    Minify(Minify, MinifyOptions),
     #[cfg(feature = "html_escape")]
     Escape(bool),
     None,
@@ -139,6 +190,10 @@ struct Extend<'a> {
     ts: &'a mut TokenStream,
     s: &'a Expr,
     concat_args: &'a mut TokenStream,
+    config: &'a Config,
+    /// Whether the markup is inside an element rendering its whitespace.
+    #[cfg(feature = "minify_document")]
+    preformatted: bool,
 }
 
 impl Extend<'_> {
@@ -154,6 +209,9 @@ impl Extend<'_> {
                 ts: &mut temp_ts,
                 s: self.s,
                 concat_args: self.concat_args,
+                config: self.config,
+                #[cfg(feature = "minify_document")]
+                preformatted: self.preformatted,
             };
             temp_extend_context.extend_childrens(childrens, false);
             temp_extend_context.concat_args_to_concat();
@@ -163,76 +221,155 @@ impl Extend<'_> {
                 }
             })
         } else {
-            for children in childrens {
-                match children {
-                    Children::Text {
-                        text,
-                        #[cfg(feature = "html_escape")]
-                        escape,
-                    } => self.extend_text(
-                        text,
-                        #[cfg(feature = "html_escape")]
-                        *escape,
-                    ),
-                    Children::Expr {
-                        expr,
-                        #[cfg(feature = "html_escape")]
-                        escape,
-                    } => self.extend_expr(
-                        expr,
-                        #[cfg(feature = "html_escape")]
-                        *escape,
-                    ),
-                    Children::CompCall {
-                        comp,
-                        ts: comp_ts,
-                        #[cfg(feature = "html_escape")]
-                        escape,
-                    } => self.extend_comp_call(
-                        comp,
-                        comp_ts,
-                        #[cfg(feature = "html_escape")]
-                        *escape,
-                    ),
-                    Children::Cond {
-                        if_,
-                        else_ifs,
-                        else_,
-                    } => self.extend_conditional(if_, else_ifs, else_),
-                    Children::For {
-                        expr_b,
-                        expr_a,
-                        childrens,
-                    } => self.extend_for(expr_b, expr_a, childrens),
-                    Children::Html {
-                        tag,
-                        attrs,
-                        childrens,
-                    } => self.extend_html(tag, attrs, childrens),
-                    Children::Match { expr, arms } => self.extend_match(expr, arms),
-                    Children::Style {
-                        text,
-                        attrs,
-                        #[cfg(feature = "minify_html")]
-                        minify,
-                    } => self.extend_style(
-                        text,
-                        attrs,
-                        #[cfg(feature = "minify_html")]
-                        *minify,
-                    ),
-                    Children::Script {
-                        text,
-                        attrs,
-                        #[cfg(feature = "minify_html")]
-                        minify,
-                    } => self.extend_script(
-                        text,
-                        attrs,
+            self.extend_sequence(childrens);
+        }
+    }
+
+    #[cfg_attr(not(feature = "minify_document"), allow(unused_variables))]
+    fn extend_sequence(&mut self, childrens: &[Children]) {
+        for (i, children) in childrens.iter().enumerate() {
+            match children {
+                Children::Text {
+                    text,
+                    #[cfg(feature = "html_escape")]
+                    escape,
+                } => self.extend_text(
+                    text,
+                    #[cfg(feature = "html_escape")]
+                    *escape,
+                ),
+                Children::Expr {
+                    expr,
+                    #[cfg(feature = "html_escape")]
+                    escape,
+                } => self.extend_expr(
+                    expr,
+                    #[cfg(feature = "html_escape")]
+                    *escape,
+                ),
+                Children::CompCall {
+                    comp,
+                    ts: comp_ts,
+                    #[cfg(feature = "html_escape")]
+                    escape,
+                } => self.extend_comp_call(
+                    comp,
+                    comp_ts,
+                    #[cfg(feature = "html_escape")]
+                    *escape,
+                ),
+                Children::Cond {
+                    if_,
+                    else_ifs,
+                    else_,
+                } => self.extend_conditional(if_, else_ifs, else_),
+                Children::For {
+                    expr_b,
+                    expr_a,
+                    childrens,
+                } => self.extend_for(expr_b, expr_a, childrens),
+                Children::Html {
+                    tag,
+                    attrs,
+                    childrens: html_childrens,
+                } => self.extend_html(
+                    tag,
+                    attrs,
+                    html_childrens,
+                    #[cfg(feature = "minify_document")]
+                    match childrens.get(i + 1) {
+                        Some(Children::Html { tag, .. }) => Following::Element(tag.to_string()),
+                        _ => Following::Unknown,
+                    },
+                ),
+                Children::Match { expr, arms } => self.extend_match(expr, arms),
+                Children::Outlet { name } => self.extend_outlet(name),
+                Children::Portal { name, childrens } => self.extend_portal(name, childrens),
+                Children::Provide { expr, childrens } => self.extend_provide(expr, childrens),
+                Children::Scope { childrens } => self.extend_childrens(childrens, false),
+                Children::Include {
+                    text,
+                    #[cfg(feature = "minify_html")]
+                    minify,
+                    src_file,
+                } => {
+                    self.extend_src_file(Some(src_file));
+                    self.extend_concat_args(text, {
+                        #[allow(unused)]
+                        let mut process_type = ProcessType::None;
                         #[cfg(feature = "minify_html")]
-                        *minify,
-                    ),
+                        if let Some(options) = minify {
+                            process_type = ProcessType::Minify(Minify::Html, options.clone());
+                        }
+                        process_type
+                    });
                 }
+                Children::Style {
+                    text,
+                    attrs,
+                    nonce,
+                    #[cfg(feature = "minify_html")]
+                    minify,
+                    once,
+                    src_file,
+                } => self.extend_once(
+                    once,
+                    || {
+                        once_key(
+                            "style",
+                            text,
+                            attrs,
+                            *nonce,
+                            self.config,
+                            #[cfg(feature = "minify_html")]
+                            minify.as_ref(),
+                        )
+                    },
+                    |extend| {
+                        extend.extend_src_file(src_file.as_ref());
+                        extend.extend_block(
+                            "style",
+                            text,
+                            attrs,
+                            *nonce,
+                            #[cfg(feature = "minify_html")]
+                            minify.as_ref(),
+                        )
+                    },
+                ),
+                Children::Script {
+                    text,
+                    attrs,
+                    nonce,
+                    #[cfg(feature = "minify_html")]
+                    minify,
+                    once,
+                    src_file,
+                } => self.extend_once(
+                    once,
+                    || {
+                        once_key(
+                            "script",
+                            text,
+                            attrs,
+                            *nonce,
+                            self.config,
+                            #[cfg(feature = "minify_html")]
+                            minify.as_ref(),
+                        )
+                    },
+                    |extend| {
+                        extend.extend_src_file(src_file.as_ref());
+                        extend.extend_block(
+                            "script",
+                            text,
+                            attrs,
+                            *nonce,
+                            #[cfg(feature = "minify_html")]
+                            minify.as_ref(),
+                        )
+                    },
+                ),
             }
         }
     }
@@ -248,27 +385,21 @@ impl Extend<'_> {
         }
     }
 
+    fn expr(&self, expr: &Expr) -> Expr {
+        UseContext { s: self.s }.rewrite(expr)
+    }
+
+    /// The render buffer, for directives that don't compile when rendering into a `String`.
+    fn buffer(&self) -> TokenStream {
+        let s = self.s;
+        quote! { ::origami_engine::__private::AsBuffer::as_buffer_mut(&mut #s) }
+    }
+
     fn extend_concat_args(&mut self, literal: &LitStr, pt: ProcessType) {
         let literal = match pt {
             #[cfg(feature = "minify_html")]
-            ProcessType::Minify(Minify::Script) => {
-                let cfg = Cfg {
-                    minify_js: true,
-                    ..Default::default()
-                };
-                let value = minify_html::minify(literal.value().as_bytes(), &cfg);
-                let value = String::from_utf8_lossy(value.as_slice());
-                &LitStr::new(&value, literal.span())
-            }
-            #[cfg(feature = "minify_html")]
-            ProcessType::Minify(Minify::Style) => {
-                let cfg = Cfg {
-                    minify_css: true,
-                    ..Default::default()
-                };
-                let value = minify_html::minify(literal.value().as_bytes(), &cfg);
-                let value = String::from_utf8_lossy(value.as_slice());
-                &LitStr::new(&value, literal.span())
+            ProcessType::Minify(minify, options) => {
+                &minify_literal(literal, minify, &options, self.config)
             }
             #[cfg(feature = "html_escape")]
             ProcessType::Escape(escape) if escape => {
@@ -291,20 +422,36 @@ impl Extend<'_> {
         {
             process_type = ProcessType::Escape(escape)
         };
+        #[cfg(feature = "minify_document")]
+        if self.config.minify_document && !self.preformatted {
+            let collapsed = minify::collapse_whitespace(&text.value());
+            self.extend_concat_args(&LitStr::new(&collapsed, text.span()), process_type);
+            return;
+        }
         self.extend_concat_args(text, process_type);
     }
 
     fn extend_expr(&mut self, expr: &Expr, #[cfg(feature = "html_escape")] escape: bool) {
         self.concat_args_to_concat();
+        let expr = self.expr(expr);
         let s = self.s;
+        #[allow(unused)]
+        let mut escape_ts = quote! { false };
         #[cfg(feature = "html_escape")]
         if escape {
-            return self.ts.extend(quote! {
-                ::origami_engine::encode_text_to_string(#expr, &mut #s);
-            });
+            escape_ts = quote! { true };
         }
         self.ts.extend(quote! {
-            #s.push_str(#expr);
+            // Temporaries of the expression live until the end of the `match`.
+            match &(#expr) {
+                __origami_expr => {
+                    #[allow(unused_imports)]
+                    use ::origami_engine::__private::{ComponentKind as _, StrKind as _, TextKind as _};
+                    (&&::origami_engine::__private::Wrap(__origami_expr))
+                        .kind()
+                        .render(&mut #s, __origami_expr, #escape_ts);
+                }
+            }
         })
     }
 
@@ -361,11 +508,13 @@ impl Extend<'_> {
         else_: &Childrens,
     ) {
         self.concat_args_to_concat();
+        let if_expr = self.expr(if_expr);
         self.ts.extend(quote! {
             if #if_expr
         });
         self.extend_childrens(if_childrens, true);
         for (else_if_expr, else_if_childrens) in else_ifs {
+            let else_if_expr = self.expr(else_if_expr);
             self.ts.extend(quote! {
                 else if #else_if_expr
             });
@@ -379,20 +528,43 @@ impl Extend<'_> {
 
     fn extend_for(&mut self, expr_b: &Expr, expr_a: &Expr, childrens: &Childrens) {
         self.concat_args_to_concat();
+        let expr_a = self.expr(expr_a);
         self.ts.extend(quote! {
             for #expr_b in #expr_a
         });
         self.extend_childrens(childrens, true);
     }
 
-    fn extend_html(&mut self, tag: &Ident, attrs: &Attributes, childrens: &HtmlChildrens) {
+    fn extend_html(
+        &mut self,
+        tag: &Ident,
+        attrs: &Attributes,
+        childrens: &HtmlChildrens,
+        #[cfg(feature = "minify_document")] following: Following,
+    ) {
         let tag_span = tag.span();
         let tag = tag.to_string();
         self.extend_concat_args(&combine_to_lit!(tag_span => "<", tag), ProcessType::None);
-        self.extend_attributes(attrs);
+        self.extend_attributes(
+            attrs,
+            #[cfg(feature = "minify_document")]
+            matches!(childrens, HtmlChildrens::SelfClosing),
+        );
         match childrens {
             HtmlChildrens::Childrens(childrens) => {
                 self.extend_concat_args(&combine_to_lit!(">"), ProcessType::None);
+                #[cfg(feature = "minify_document")]
+                {
+                    let preformatted = self.preformatted;
+                    self.preformatted |= minify::is_preformatted(&tag);
+                    self.extend_childrens(childrens, false);
+                    self.preformatted = preformatted;
+                    if self.config.minify_document && minify::is_end_tag_optional(&tag, &following)
+                    {
+                        return;
+                    }
+                }
+                #[cfg(not(feature = "minify_document"))]
                 self.extend_childrens(childrens, false);
                 self.extend_concat_args(
                     &combine_to_lit!(tag_span => "</", tag, ">"),
@@ -400,13 +572,30 @@ impl Extend<'_> {
                 );
             }
             HtmlChildrens::SelfClosing => {
-                self.extend_concat_args(&combine_to_lit!("/>"), ProcessType::None);
+                let end = if self.config.self_closing_slash {
+                    "/>"
+                } else {
+                    ">"
+                };
+                self.extend_concat_args(&combine_to_lit!(end), ProcessType::None);
             }
         }
     }
 
-    fn extend_attributes(&mut self, attributes: &Attributes) {
-        for (k, v) in &attributes.0 {
+    fn extend_attributes(
+        &mut self,
+        attributes: &Attributes,
+        #[cfg(feature = "minify_document")] self_closing: bool,
+    ) {
+        // The value of the last attribute of a self-closing tag keeps its quotes.
+        #[cfg(feature = "minify_document")]
+        let last = attributes
+            .0
+            .keys()
+            .rposition(|k| matches!(k, AttributeKey::Ident(_) | AttributeKey::LitStr(_)))
+            .filter(|_| self_closing && self.config.self_closing_slash);
+        #[cfg_attr(not(feature = "minify_document"), allow(unused_variables))]
+        for (i, (k, v)) in attributes.0.iter().enumerate() {
             match k {
                 AttributeKey::Ident(ident) => {
                     self.extend_concat_args(
@@ -432,12 +621,26 @@ impl Extend<'_> {
             if let Some(v) = v {
                 match v {
                     AttributeValue::LitStr(literal) => {
-                        self.extend_concat_args(
-                            &combine_to_lit!(literal.span() => "=\"", literal.value(), "\""),
-                            ProcessType::None,
-                        );
+                        #[allow(unused)]
+                        let mut value =
+                            combine_to_lit!(literal.span() => "=\"", literal.value(), "\"");
+                        #[cfg(feature = "minify_document")]
+                        if self.config.minify_document {
+                            let name = match k {
+                                AttributeKey::Ident(ident) => ident.to_string(),
+                                AttributeKey::LitStr(literal) => literal.value(),
+                                _ => String::new(),
+                            };
+                            let minified = minify::attribute_value(&name, &literal.value());
+                            value = LitStr::new(
+                                &minify::quote(&minified, last == Some(i)),
+                                literal.span(),
+                            );
+                        }
+                        self.extend_concat_args(&value, ProcessType::None);
                     }
                     AttributeValue::Expr(expr) => {
+                        let expr = self.expr(expr);
                         let s = self.s;
                         self.extend_concat_args(&combine_to_lit!("=\""), ProcessType::None);
                         self.concat_args_to_concat();
@@ -458,6 +661,9 @@ impl Extend<'_> {
             s: self.s,
             ts: &mut temp,
             concat_args: self.concat_args,
+            config: self.config,
+            #[cfg(feature = "minify_document")]
+            preformatted: self.preformatted,
         };
         for CustomMatchArm {
             body,
@@ -467,6 +673,7 @@ impl Extend<'_> {
         } in arms
         {
             let guard = if let Some((if_, expr)) = guard {
+                let expr = temp_extend.expr(expr);
                 quote! {
                     #if_ #expr
                 }
@@ -482,6 +689,7 @@ impl Extend<'_> {
                 #comma
             });
         }
+        let expr = self.expr(expr);
         self.ts.extend(quote! {
             match #expr {
                 #temp
@@ -489,53 +697,313 @@ impl Extend<'_> {
         });
     }
 
-    fn extend_style(
+    /// Makes the crate depend on the included file, so that it is recompiled when the file changes.
+    fn extend_src_file(&mut self, src_file: Option<&LitStr>) {
+        if let Some(src_file) = src_file {
+            self.ts.extend(quote! {
+                const _: &[u8] = include_bytes!(#src_file);
+            });
+        }
+    }
+
+    fn extend_outlet(&mut self, name: &Ident) {
+        self.concat_args_to_concat();
+        let buffer = self.buffer();
+        let name = LitStr::new(&name.to_string(), name.span());
+        self.ts.extend(quote! {
+            #buffer.outlet(#name);
+        });
+    }
+
+    fn extend_portal(&mut self, name: &Ident, childrens: &Childrens) {
+        self.concat_args_to_concat();
+        let s = self.s;
+        let name = LitStr::new(&name.to_string(), name.span());
+        let mut temp_ts = TokenStream::new();
+        let mut temp_extend_context = Extend {
+            ts: &mut temp_ts,
+            s: self.s,
+            concat_args: self.concat_args,
+            config: self.config,
+            #[cfg(feature = "minify_document")]
+            preformatted: self.preformatted,
+        };
+        temp_extend_context.extend_childrens(childrens, false);
+        temp_extend_context.concat_args_to_concat();
+        let buffer = self.buffer();
+        self.ts.extend(quote! {
+            {
+                let __origami_portal_start = #s.len();
+                #temp_ts
+                #buffer.portal(#name, __origami_portal_start);
+            }
+        });
+    }
+
+    /// Renders a block with `extend`, only the first time it is rendered if `once` is set.
+    fn extend_once(
         &mut self,
+        once: &Option<Once>,
+        key: impl FnOnce() -> u64,
+        extend: impl Fn(&mut Extend),
+    ) {
+        let Some(Once { outlet }) = once else {
+            extend(self);
+            return;
+        };
+        self.concat_args_to_concat();
+        let key = key();
+        let mut temp_ts = TokenStream::new();
+        let mut temp_extend_context = Extend {
+            ts: &mut temp_ts,
+            s: self.s,
+            concat_args: self.concat_args,
+            config: self.config,
+            #[cfg(feature = "minify_document")]
+            preformatted: self.preformatted,
+        };
+        extend(&mut temp_extend_context);
+        temp_extend_context.concat_args_to_concat();
+        let s = self.s;
+        let buffer = self.buffer();
+        if let Some(outlet) = outlet {
+            let name = LitStr::new(&outlet.to_string(), outlet.span());
+            temp_ts = quote! {
+                let __origami_portal_start = #s.len();
+                #temp_ts
+                #buffer.portal(#name, __origami_portal_start);
+            };
+        }
+        self.ts.extend(quote! {
+            if #buffer.once(#key) {
+                #temp_ts
+            }
+        });
+    }
+
+    fn extend_provide(&mut self, expr: &Expr, childrens: &Childrens) {
+        self.concat_args_to_concat();
+        let expr = self.expr(expr);
+        let buffer = self.buffer();
+        self.ts.extend(quote! {
+            #buffer.provide(#expr);
+        });
+        self.extend_childrens(childrens, true);
+        self.ts.extend(quote! {
+            #buffer.pop_context();
+        });
+    }
+
+    fn extend_block(
+        &mut self,
+        tag: &str,
         text: &Option<LitStr>,
         attrs: &Attributes,
-        #[cfg(feature = "minify_html")] minify: bool,
+        nonce: bool,
+        #[cfg(feature = "minify_html")] minify: Option<&MinifyOptions>,
     ) {
-        self.extend_concat_args(&combine_to_lit!("<style"), ProcessType::None);
-        self.extend_attributes(attrs);
-        self.extend_concat_args(&combine_to_lit!(">"), ProcessType::None);
-        if let Some(text) = text {
-            self.extend_concat_args(text, {
-                #[allow(unused)]
-                let mut process_type = ProcessType::None;
+        let directive = directive(tag);
+        self.extend_concat_args(&combine_to_lit!("<", tag), ProcessType::None);
+        self.extend_attributes(
+            attrs,
+            #[cfg(feature = "minify_document")]
+            false,
+        );
+        let text = text.as_ref().map(|text| {
+            block_text(
+                tag,
+                text,
+                self.config,
                 #[cfg(feature = "minify_html")]
+                minify,
+            )
+        });
+        let source = text.as_ref().map(|text| csp::source(&text.value()));
+        let s = self.s;
+        if nonce {
+            self.concat_args_to_concat();
+            // Without a nonce in the context, the block is allowed by its hash.
+            let buffer = self.buffer();
+            let source = source.iter();
+            self.ts.extend(quote! {
+                if !#buffer.push_nonce(#directive) {
+                    #(#buffer.csp_source(#directive, #source);)*
+                }
+            });
+        } else if let Some(source) = source {
+            self.ts.extend(quote! {
                 {
-                    if minify {
-                        process_type = ProcessType::Minify(Minify::Style);
-                    }
+                    #[allow(unused_imports)]
+                    use ::origami_engine::__private::Output as _;
+                    #s.csp_source(#directive, #source);
                 }
-                process_type
             });
         }
-        self.extend_concat_args(&combine_to_lit!("</style>"), ProcessType::None);
+        self.extend_concat_args(&combine_to_lit!(">"), ProcessType::None);
+        if let Some(text) = text {
+            self.extend_concat_args(&text, ProcessType::None);
+        }
+        self.extend_concat_args(&combine_to_lit!("</", tag, ">"), ProcessType::None);
     }
+}
 
-    fn extend_script(
+/// Key of a `once` block, the SHA-256 of the block as it is rendered, with the minified text
+/// and the tokens of the attribute expressions. Identical blocks get the same key, however their
+/// literals are written.
+fn once_key(
+    tag: &str,
+    text: &Option<LitStr>,
+    attrs: &Attributes,
+    nonce: bool,
+    config: &Config,
+    #[cfg(feature = "minify_html")] minify: Option<&MinifyOptions>,
+) -> u64 {
+    let mut content = format!("<{}", tag);
+    for (key, value) in &attrs.0 {
+        match key {
+            AttributeKey::Ident(ident) => content.push_str(&format!(" {}", ident)),
+            AttributeKey::LitStr(lit) => content.push_str(&format!(" {}", lit.value())),
+            #[allow(unreachable_patterns)]
+            _ => continue,
+        }
+        match value {
+            Some(AttributeValue::LitStr(lit)) => content.push_str(&format!("=\"{}\"", lit.value())),
+            Some(AttributeValue::Expr(expr)) => {
+                content.push_str(&format!("={{{}}}", expr.to_token_stream()))
+            }
+            None => {}
+        }
+    }
+    if nonce {
+        content.push_str(" nonce");
+    }
+    content.push('>');
+    if let Some(text) = text {
+        let text = block_text(
+            tag,
+            text,
+            config,
+            #[cfg(feature = "minify_html")]
+            minify,
+        );
+        content.push_str(&text.value());
+    }
+    let digest = csp::sha256(&content);
+    u64::from_be_bytes(digest[..8].try_into().expect("digest is 32 bytes"))
+}
+
+/// Content of a `script` or `style` block as it is rendered.
+#[cfg_attr(not(feature = "minify_html"), allow(unused_variables))]
+fn block_text(
+    tag: &str,
+    text: &LitStr,
+    config: &Config,
+    #[cfg(feature = "minify_html")] minify: Option<&MinifyOptions>,
+) -> LitStr {
+    #[cfg(feature = "minify_html")]
+    if let Some(options) = minify {
+        let minify = if tag == "script" {
+            Minify::Script
+        } else {
+            Minify::Style
+        };
+        return minify_literal(text, minify, options, config);
+    }
+    text.clone()
+}
+
+#[cfg(feature = "minify_html")]
+fn minify_literal(
+    literal: &LitStr,
+    minify: Minify,
+    options: &MinifyOptions,
+    config: &Config,
+) -> LitStr {
+    let mut cfg = match minify {
+        Minify::Script => Cfg {
+            minify_js: true,
+            ..Default::default()
+        },
+        Minify::Style => Cfg {
+            minify_css: true,
+            ..Default::default()
+        },
+        Minify::Html => Cfg {
+            minify_css: true,
+            minify_js: true,
+            ..Default::default()
+        },
+    };
+    // Options of the crate come first, those of the block override them.
+    config.apply_minify(&mut cfg);
+    options.apply(&mut cfg);
+    let value = minify_html::minify(literal.value().as_bytes(), &cfg);
+    LitStr::new(&String::from_utf8_lossy(&value), literal.span())
+}
+
+/// Directive allowing blocks with `tag`.
+fn directive(tag: &str) -> TokenStream {
+    if tag == "script" {
+        quote! { ::origami_engine::csp::Directive::Script }
+    } else {
+        quote! { ::origami_engine::csp::Directive::Style }
+    }
+}
+
+/// Collects the hashes of the `script` and `style` blocks of a template.
+struct CspSources<'a> {
+    config: &'a Config,
+    ts: TokenStream,
+}
+
+impl CspSources<'_> {
+    fn add(
         &mut self,
-        text: &Option<LitStr>,
-        attrs: &Attributes,
-        #[cfg(feature = "minify_html")] minify: bool,
+        tag: &str,
+        text: &LitStr,
+        #[cfg(feature = "minify_html")] minify: &Option<MinifyOptions>,
     ) {
-        self.extend_concat_args(&combine_to_lit!("<script"), ProcessType::None);
-        self.extend_attributes(attrs);
-        self.extend_concat_args(&combine_to_lit!(">"), ProcessType::None);
-        if let Some(text) = text {
-            self.extend_concat_args(text, {
-                #[allow(unused)]
-                let mut process_type = ProcessType::None;
+        let text = block_text(
+            tag,
+            text,
+            self.config,
+            #[cfg(feature = "minify_html")]
+            minify.as_ref(),
+        );
+        let directive = directive(tag);
+        let source = csp::source(&text.value());
+        self.ts.extend(quote! {
+            csp.add(#directive, #source);
+        });
+    }
+}
+
+impl Visit for CspSources<'_> {
+    fn visit_children(&mut self, children: &Children) {
+        match children {
+            Children::Script {
+                text: Some(text),
                 #[cfg(feature = "minify_html")]
-                {
-                    if minify {
-                        process_type = ProcessType::Minify(Minify::Script);
-                    }
-                }
-                process_type
-            });
+                minify,
+                ..
+            } => self.add(
+                "script",
+                text,
+                #[cfg(feature = "minify_html")]
+                minify,
+            ),
+            Children::Style {
+                text: Some(text),
+                #[cfg(feature = "minify_html")]
+                minify,
+                ..
+            } => self.add(
+                "style",
+                text,
+                #[cfg(feature = "minify_html")]
+                minify,
+            ),
+            _ => visit::visit_children(self, children),
         }
-        self.extend_concat_args(&combine_to_lit!("</script>"), ProcessType::None);
     }
 }
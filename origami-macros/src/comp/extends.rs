@@ -0,0 +1,131 @@
+use indexmap::IndexMap;
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::spanned::Spanned;
+use syn::{braced, bracketed, Ident, Token};
+
+use crate::utils::bail;
+
+/// A call of a component through `extends`, with the overridden blocks in any order.
+pub struct Extends {
+    comp: TokenStream,
+    escape: TokenStream,
+    internal: TokenStream,
+    blocks: IndexMap<Ident, TokenStream>,
+    /// Errors in the overridden blocks, reported along with the call so it still expands.
+    error: Option<syn::Error>,
+}
+
+impl Parse for Extends {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let content;
+        bracketed!(content in input);
+        let comp: TokenStream = content.parse()?;
+        let escape = parse_section(input, "escape")?;
+        input.parse::<Token![,]>()?;
+        let internal = parse_section(input, "internal")?;
+        input.parse::<Token![,]>()?;
+        input.parse::<Ident>()?;
+        let content;
+        bracketed!(content in input);
+        let mut props = Vec::new();
+        while !content.is_empty() {
+            let prop: Ident = content.parse()?;
+            let optional = content.parse::<Option<Token![?]>>()?.is_some();
+            props.push((prop, optional));
+        }
+        input.parse::<Token![,]>()?;
+        let overrides = parse_section(input, "blocks")?;
+        let mut blocks = IndexMap::new();
+        let mut error = None::<syn::Error>;
+        let mut report = |e: syn::Error| match &mut error {
+            Some(error) => error.combine(e),
+            None => error = Some(e),
+        };
+        let parse_blocks = |input: ParseStream| {
+            while !input.is_empty() {
+                let block: Ident = input.parse()?;
+                if block != "block" {
+                    bail!(block, "Expected `block`");
+                }
+                let name: Ident = input.parse()?;
+                let content;
+                braced!(content in input);
+                let ts: TokenStream = content.parse()?;
+                if !props.iter().any(|(prop, _)| *prop == name) {
+                    report(syn::Error::new(
+                        name.span(),
+                        format!("Unknown block `{}`", name),
+                    ));
+                } else if blocks.contains_key(&name) {
+                    report(syn::Error::new(
+                        name.span(),
+                        format!("Block `{}` is overridden twice", name),
+                    ));
+                } else {
+                    blocks.insert(name, ts);
+                }
+                input.parse::<Option<Token![,]>>()?;
+            }
+            Ok(())
+        };
+        syn::parse::Parser::parse2(parse_blocks, overrides)?;
+        if let Some((prop, _)) = props
+            .iter()
+            .find(|(prop, optional)| !optional && !blocks.contains_key(prop))
+        {
+            bail!(comp, format!("Missing block `{}`", prop));
+        }
+        // Blocks are passed to the component in declaration order.
+        let blocks = props
+            .into_iter()
+            .filter_map(|(prop, _)| blocks.shift_remove_entry(&prop))
+            .collect();
+        Ok(Extends {
+            comp,
+            escape,
+            internal,
+            blocks,
+            error,
+        })
+    }
+}
+
+fn parse_section(input: ParseStream, name: &str) -> syn::Result<TokenStream> {
+    let ident: Ident = input.parse()?;
+    if ident != name {
+        bail!(ident, format!("Expected `{}`", name));
+    }
+    let content;
+    braced!(content in input);
+    content.parse()
+}
+
+impl ToTokens for Extends {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let Extends {
+            comp,
+            escape,
+            internal,
+            blocks,
+            error,
+        } = self;
+        if let Some(error) = error {
+            tokens.extend(error.to_compile_error());
+        }
+        let blocks = blocks.iter().map(|(block, ts)| {
+            quote! {
+                , #block { #ts }
+            }
+        });
+        tokens.extend(quote! {
+            #comp! {
+                @component
+                escape { #escape },
+                internal { #internal }
+                #(#blocks)*
+            }
+        });
+    }
+}
@@ -0,0 +1,67 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use proc_macro2::{Span, TokenStream, TokenTree};
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream, Parser};
+use syn::{LitStr, Token};
+
+use super::Component;
+use crate::utils::{bail, read_manifest_file};
+
+/// A component whose body is read from a template file.
+pub struct ComponentFile {
+    component: Component,
+    full_path: String,
+}
+
+impl Parse for ComponentFile {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut header = TokenStream::new();
+        while !input.peek(Token![=>]) {
+            if input.is_empty() {
+                bail!(input, "Expected `=> \"path\"`");
+            }
+            header.extend(Some(input.parse::<TokenTree>()?));
+        }
+        let arrow = input.parse::<Token![=>]>()?;
+        let path: LitStr = input.parse()?;
+        if !input.is_empty() {
+            bail!(input, "Expected end of input after the template path");
+        }
+        let (text, full_path) = read_manifest_file(&path)?;
+        let in_file = |message: &dyn Display| {
+            syn::Error::new(path.span(), format!("{}: {}", path.value(), message))
+        };
+        // Tokens of the file have the span of the call, errors on them are reported at its path.
+        let error = |e: syn::Error| {
+            if e.span().start() == Span::call_site().start() {
+                in_file(&e)
+            } else {
+                e
+            }
+        };
+        let body = TokenStream::from_str(&text).map_err(|e| in_file(&e))?;
+        #[allow(unused_mut)]
+        let mut component = Component::parse
+            .parse2(quote! { #header #arrow #body })
+            .map_err(error)?;
+        component.check_body().map_err(error)?;
+        #[cfg(feature = "hot_reload")]
+        component.enable_hot_reload(full_path.clone())?;
+        Ok(Self {
+            component,
+            full_path,
+        })
+    }
+}
+
+impl ToTokens for ComponentFile {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let full_path = &self.full_path;
+        self.component.to_tokens(tokens);
+        tokens.extend(quote! {
+            const _: &[u8] = include_bytes!(#full_path);
+        });
+    }
+}
@@ -0,0 +1,177 @@
+use indexmap::{IndexMap, IndexSet};
+use origami_syntax::visit::Visit;
+use origami_syntax::{parse_childrens, Context};
+use quote::quote;
+use syn::parse::{ParseStream, Parser};
+use syn::{Expr, ExprLit, Ident, Lit};
+
+use super::{bound_idents, placeholder_substitutions, Component, MacroRep};
+
+/// What the generated code passes to the template file when it is reloaded.
+pub struct HotReload {
+    path: String,
+    /// Markup of the props, rendered before the template and inserted where it renders them.
+    props: IndexMap<Ident, proc_macro2::TokenStream>,
+    /// Variables of the enclosing scope read by the template.
+    vars: IndexSet<Ident>,
+}
+
+impl Component {
+    /// Renders the component from the template file at `path` when it changes, in debug builds.
+    ///
+    /// Components rendered by a generated function, or reading a prop as an expression, read
+    /// props the runtime cannot evaluate, so they always render the compiled template.
+    pub(super) fn enable_hot_reload(&mut self, path: String) -> syn::Result<()> {
+        if self.typed {
+            return Ok(());
+        }
+        let Some(vars) = self.vars() else {
+            return Ok(());
+        };
+        // Lints were reported when the component was parsed.
+        let mut warnings = proc_macro2::TokenStream::new();
+        let mut rep = MacroRep::new(
+            &self.props,
+            self.export,
+            false,
+            None,
+            IndexSet::new(),
+            &self.config,
+            &mut warnings,
+        );
+        let mut props = IndexMap::new();
+        for prop in self.props.keys() {
+            // Scoped slots take arguments, they are not supported by reloaded templates.
+            if !self.slots.contains_key(prop) {
+                props.insert(prop.clone(), rep.extend_prop(prop)?);
+            }
+        }
+        self.hot_reload = Some(HotReload { path, props, vars });
+        Ok(())
+    }
+
+    /// Collects the variables of the enclosing scope the body reads, i.e. the variables of
+    /// expressions that are neither props nor bound by the template, or `None` when the body
+    /// reads a prop as an expression.
+    fn vars(&self) -> Option<IndexSet<Ident>> {
+        let mut locals = IndexSet::new();
+        bound_idents(self.ts.clone(), &mut locals);
+        let mut vars = Vars::default();
+        let ts = placeholder_substitutions(self.ts.clone());
+        if let Ok(childrens) =
+            (|input: ParseStream| parse_childrens(input, &mut Context::default())).parse2(ts)
+        {
+            vars.visit_childrens(&childrens);
+        }
+        if vars.prop_expr {
+            return None;
+        }
+        let vars = vars
+            .vars
+            .into_iter()
+            .filter(|var| {
+                // Constants and unit variants are not data of the template.
+                let lowercase = var
+                    .to_string()
+                    .starts_with(|c: char| c.is_lowercase() || c == '_');
+                lowercase && var != "self" && !locals.contains(var) && !self.props.contains_key(var)
+            })
+            .collect();
+        Some(vars)
+    }
+
+    pub(super) fn hot_reload_ts(
+        &self,
+        HotReload { path, props, vars }: &HotReload,
+        escape: &proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        let ts = &self.ts;
+        let config = self.config.anon_arg();
+        let prop_names = props.keys().map(ToString::to_string);
+        let props = props.values();
+        let var_names = vars.iter().map(ToString::to_string);
+        let vars = vars.iter();
+        quote! {
+            @::origami_engine::FnComponent(|#[allow(unused_mut)] mut s: &mut ::origami_engine::Buffer| {
+                #[cfg(debug_assertions)]
+                if let Some(template) = ::origami_engine::__private::hot_template(#path, include_str!(#path)) {
+                    #[allow(unused_imports)]
+                    use ::origami_engine::__private::{OpaqueData as _, SerializeData as _};
+                    #[allow(unused_mut)]
+                    let mut data = ::origami_engine::__private::Map::new();
+                    #(
+                        if let Some(value) = (&&::origami_engine::__private::Data(&#vars)).value() {
+                            data.insert(#var_names.to_owned(), value);
+                        }
+                    )*
+                    // Props are rendered into a fork of the buffer, keeping the provided context.
+                    let props = [#(
+                        (#prop_names, ::origami_engine::__private::hot_prop(s, |#[allow(unused_mut)] mut prop| {
+                            ::origami_engine::anon! {
+                                string prop,
+                                #config
+                                childrens #escape {
+                                    #props
+                                }
+                            }
+                        }))
+                    ),*];
+                    if ::origami_engine::__private::hot_render(s, #path, &template, &props, data) {
+                        return;
+                    }
+                }
+                ::origami_engine::anon! {
+                    string s,
+                    #config
+                    childrens #escape {
+                        #ts
+                    }
+                }
+            });
+        }
+    }
+}
+
+/// Visits the variables of expressions the runtime can evaluate.
+#[derive(Default)]
+struct Vars {
+    vars: IndexSet<Ident>,
+    /// Whether an expression reads a prop, i.e. its `""` placeholder.
+    prop_expr: bool,
+}
+
+impl Visit for Vars {
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Path(path) if path.qself.is_none() => {
+                if let Some(ident) = path.path.get_ident() {
+                    self.vars.insert(ident.clone());
+                }
+            }
+            Expr::Lit(ExprLit {
+                lit: Lit::Str(lit), ..
+            }) if lit.value().is_empty() => self.prop_expr = true,
+            Expr::Field(field) => self.visit_expr(&field.base),
+            Expr::Index(index) => {
+                self.visit_expr(&index.expr);
+                self.visit_expr(&index.index);
+            }
+            Expr::MethodCall(call) => self.visit_expr(&call.receiver),
+            Expr::Unary(unary) => self.visit_expr(&unary.expr),
+            Expr::Reference(reference) => self.visit_expr(&reference.expr),
+            Expr::Paren(paren) => self.visit_expr(&paren.expr),
+            Expr::Group(group) => self.visit_expr(&group.expr),
+            Expr::Binary(binary) => {
+                self.visit_expr(&binary.left);
+                self.visit_expr(&binary.right);
+            }
+            Expr::Array(array) => array.elems.iter().for_each(|expr| self.visit_expr(expr)),
+            Expr::Tuple(tuple) => tuple.elems.iter().for_each(|expr| self.visit_expr(expr)),
+            Expr::Range(range) => {
+                range.start.iter().for_each(|expr| self.visit_expr(expr));
+                range.end.iter().for_each(|expr| self.visit_expr(expr));
+            }
+            _ => {}
+        }
+    }
+}
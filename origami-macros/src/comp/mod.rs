@@ -1,24 +1,86 @@
-use indexmap::IndexSet;
-use proc_macro2::{Delimiter, TokenTree};
-use quote::{quote, ToTokens};
-use syn::parse::{Parse, ParseStream};
+use indexmap::{IndexMap, IndexSet};
+use proc_macro2::{Delimiter, Group, Punct, Spacing, TokenTree};
+use quote::{format_ident, quote, ToTokens};
+use syn::parse::{Parse, ParseStream, Parser};
+use syn::punctuated::Punctuated;
 use syn::token::Paren;
-use syn::{parenthesized, Ident, Token, Visibility};
+use syn::{braced, parenthesized, Attribute, Expr, Generics, Ident, Token, Type, Visibility};
 
-use crate::utils::bail;
+use crate::anon::csp;
+use crate::config::{Config, Level};
+use crate::utils::{bail, warning};
+
+pub use self::extends::Extends;
+pub use self::file::ComponentFile;
+
+mod extends;
+mod file;
+#[cfg(feature = "hot_reload")]
+mod hot_reload;
 
 pub struct Component {
+    attrs: Vec<Attribute>,
+    export: bool,
+    recursive: bool,
     vis: Visibility,
     name: syn::Ident,
+    generics: proc_macro2::TokenStream,
+    where_clause: proc_macro2::TokenStream,
+    /// Whether the component is rendered by a generated function, i.e. it has generics or typed props.
+    typed: bool,
     ts: proc_macro2::TokenStream,
-    props: IndexSet<Ident>,
+    props: IndexMap<Ident, Prop>,
+    slots: IndexMap<Ident, usize>,
+    /// Configuration of the crate with the settings of `#[origami(..)]`.
+    config: Config,
+    /// Warnings of the lints, emitted with the component.
+    warnings: proc_macro2::TokenStream,
+    /// Template file the body is reloaded from, set by `comp_file!`.
+    #[cfg(feature = "hot_reload")]
+    hot_reload: Option<hot_reload::HotReload>,
+}
+
+struct Prop {
+    ty: Option<proc_macro2::TokenStream>,
+    /// Fallback markup, if the prop is optional.
+    fallback: Option<proc_macro2::TokenStream>,
 }
 
 impl Parse for Component {
     fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut attrs = input.call(Attribute::parse_outer)?;
+        let mut export = false;
+        let mut recursive = false;
+        let mut config = Config::load()?;
+        let mut overrides = Vec::new();
+        attrs.retain(|attr| {
+            if attr.path().is_ident("origami") {
+                overrides.push(attr.parse_args::<proc_macro2::TokenStream>());
+                return false;
+            }
+            if attr.path().is_ident("macro_export") {
+                export = true;
+                return false;
+            }
+            if attr.path().is_ident("recursive") {
+                recursive = true;
+                return false;
+            }
+            true
+        });
+        for ts in overrides {
+            config.apply_overrides(ts?)?;
+        }
         let vis = input.parse()?;
-        let name = input.parse()?;
-        let mut props = IndexSet::new();
+        if export && !matches!(vis, Visibility::Public(_)) {
+            bail!(input, "`#[macro_export]` components must be declared `pub`");
+        }
+        if export {
+            config.bake();
+        }
+        let name: Ident = input.parse()?;
+        let mut generics: Generics = input.parse()?;
+        let mut props = IndexMap::new();
         if input.peek(Paren) {
             let content;
             parenthesized!(content in input);
@@ -35,139 +97,1141 @@ impl Parse for Component {
                     }
                 }
                 let prop: Ident = content.parse()?;
-                if props.contains(&prop) {
+                if props.contains_key(&prop) {
                     bail!(prop, "Duplicate prop: `{}`");
                 }
-                props.insert(prop);
+                let ty = if content.peek(Token![:]) {
+                    content.parse::<Token![:]>()?;
+                    let ty: Type = content.parse()?;
+                    Some(dollar_crate(ty.into_token_stream(), export))
+                } else {
+                    None
+                };
+                let fallback = if content.peek(Token![=]) {
+                    content.parse::<Token![=]>()?;
+                    let fallback;
+                    braced!(fallback in content);
+                    Some(fallback.parse()?)
+                } else {
+                    None
+                };
+                props.insert(prop, Prop { ty, fallback });
                 count += 1;
             }
         }
+        generics.where_clause = input.parse()?;
         input.parse::<Token![=>]>()?;
-        let ts = macro_rep(input, &props)?;
+        let body: proc_macro2::TokenStream = input.parse()?;
+        let scope = has_scoped_style(body.clone()).then(|| {
+            let digest = csp::sha256(&format!("{}\n{}", name, body));
+            format!(
+                "data-o-{:08x}",
+                u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]])
+            )
+        });
+        let typed = recursive
+            || !generics.params.is_empty()
+            || props.values().any(|prop| prop.ty.is_some());
+        let mut blocks = IndexMap::new();
+        collect_blocks(body.clone(), &mut blocks)?;
+        if let Some(block) = blocks.keys().next() {
+            if typed {
+                bail!(
+                    block,
+                    "Blocks are not supported in recursive components or components with generics or typed props"
+                );
+            }
+        }
+        for (block, fallback) in blocks {
+            if props.contains_key(&block) {
+                bail!(block, format!("Block `{}` conflicts with a prop", block));
+            }
+            props.insert(
+                block,
+                Prop {
+                    ty: None,
+                    fallback: Some(fallback),
+                },
+            );
+        }
+        let mut warnings = proc_macro2::TokenStream::new();
+        let (ts, slots) = if typed {
+            if let Some((prop, _)) = props.iter().find(|(_, prop)| prop.fallback.is_some()) {
+                bail!(
+                    prop,
+                    "Fallback content is not supported in recursive components or components with generics or typed props"
+                );
+            }
+            // Props are parameters of the generated function, so they are not substituted.
+            macro_rep(
+                body,
+                &IndexMap::new(),
+                export,
+                scope.is_some(),
+                recursive.then_some((&name, &props)),
+                &config,
+                &mut warnings,
+            )?
+        } else {
+            macro_rep(
+                body,
+                &props,
+                export,
+                scope.is_some(),
+                None,
+                &config,
+                &mut warnings,
+            )?
+        };
+        let sigil = Punct::new('#', Spacing::Alone);
+        let ts = match scope {
+            Some(scope) => quote! {
+                #sigil scope #scope {
+                    #ts
+                }
+            },
+            None => ts,
+        };
         Ok(Component {
+            attrs,
+            export,
+            recursive,
             vis,
             name,
+            where_clause: dollar_crate(generics.where_clause.to_token_stream(), export),
+            generics: dollar_crate(generics.to_token_stream(), export),
+            typed,
             ts,
             props,
+            slots,
+            config,
+            warnings,
+            #[cfg(feature = "hot_reload")]
+            hot_reload: None,
         })
     }
 }
 
 impl ToTokens for Component {
     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
-        let props = self.props.iter();
+        let props = self
+            .props
+            .keys()
+            .map(|prop| self.prop_pattern(prop, quote! { , }));
         let front_comma_props = quote! {
-            #(, #props {$($#props:tt)*})*
+            #(#props)* $(,)?
         };
-        let props = self.props.iter();
-        let back_comma_props = quote! {
-            #(#props {$($#props:tt)*}),*
+        let props = self
+            .props
+            .keys()
+            .map(|prop| self.prop_pattern(prop, quote! { $(,)? }));
+        let loose_comma_props = quote! {
+            #(#props)* $(,)?
         };
         let name = &self.name;
         let vis = &self.vis;
-        let vis_t = {
-            if vis == &syn::Visibility::Inherited {
-                quote! {}
-            } else {
-                quote! { #vis use #name; }
+        let attrs = &self.attrs;
+        let (macro_name, macro_attrs, vis_t) = if self.export {
+            // `#[macro_export]` macros generated by another macro cannot be referred to by
+            // absolute paths inside their own crate, so the real macro gets a hidden name
+            // and is re-exported under the component name. Its suffix is derived from the crate
+            // and component names, so that expansions are the same from one build to the next.
+            let krate = std::env::var("CARGO_CRATE_NAME").unwrap_or_default();
+            let digest = csp::sha256(&format!("{}::{}", krate, name));
+            let suffix = digest[..8]
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<String>();
+            let hidden = format_ident!("__origami_{}_{}", name, suffix);
+            (
+                hidden.clone(),
+                quote! {
+                    #[doc(hidden)]
+                    #[macro_export]
+                },
+                quote! {
+                    #(#attrs)*
+                    #[doc(inline)]
+                    #vis use #hidden as #name;
+                },
+            )
+        } else if vis == &syn::Visibility::Inherited {
+            (name.clone(), quote! { #(#attrs)* }, quote! {})
+        } else {
+            (
+                name.clone(),
+                quote! { #(#attrs)* },
+                quote! { #vis use #name; },
+            )
+        };
+        let component_ts = self.render_ts(quote! { $($escape)* });
+        let ts = self.render_ts(quote! {});
+        let config = self.config.anon_arg();
+        let extends_arm = (!self.typed).then(|| {
+            let blocks = self
+                .props
+                .iter()
+                .filter(|(prop, _)| !self.slots.contains_key(*prop))
+                .map(|(prop, Prop { fallback, .. })| {
+                    let optional = fallback.as_ref().map(|_| quote! { ? });
+                    quote! { #prop #optional }
+                });
+            quote! {
+                (@component escape { $($escape:tt)* }, internal { $($internal:tt)* }, extends [$($__comp:tt)*] { $($__blocks:tt)* }) => {
+                    ::origami_engine::__extends! {
+                        [$($__comp)*]
+                        escape { $($escape)* },
+                        internal { $($internal)* },
+                        props [#(#blocks)*],
+                        blocks { $($__blocks)* }
+                    }
+                };
             }
+        });
+        // Errors are reported where the hashes are used, the body is checked where it is called.
+        let csp = match self.parse_body() {
+            Ok(anon) => anon.csp(),
+            Err(err) => err.to_compile_error(),
         };
-        let ts = &self.ts;
+        let warnings = &self.warnings;
         tokens.extend(quote! {
-            macro_rules! #name {
-              (@component escape { $($escape:tt)* }, internal { $($internal:tt)* }, #back_comma_props) => {
+            #warnings
+            #macro_attrs
+            macro_rules! #macro_name {
+              #extends_arm
+              (@component escape { $($escape:tt)* }, internal { $($internal:tt)* } #loose_comma_props) => {
                   ::origami_engine::anon! {
                       $($internal)*,
+                      #config
                       childrens $($escape)* {
-                          #ts
+                          #component_ts
                       }
                   }
               };
-              (#back_comma_props) => {{
-                  let mut s = String::new();
+              (csp) => {
+                  #csp
+              };
+              (#loose_comma_props) => {{
+                  let mut s = ::origami_engine::Buffer::new();
                   ::origami_engine::anon! {
+                      #config
                       childrens {
                           #ts
                       }
                   }
-                  ::origami_engine::Origami(s)
+                  ::origami_engine::Origami::from(s)
               }};
               (cap => $capacity:expr #front_comma_props) => {{
-                  let mut s = String::with_capacity($capacity);
+                  let mut s = ::origami_engine::Buffer::with_capacity($capacity);
                   ::origami_engine::anon! {
+                      #config
                       childrens {
                           #ts
                       }
                   }
-                  ::origami_engine::Origami(s)
+                  ::origami_engine::Origami::from(s)
               }};
+              (dyn #front_comma_props) => {
+                  ::origami_engine::FnComponent(move |#[allow(unused_mut)] mut s: &mut ::origami_engine::Buffer| {
+                      ::origami_engine::anon! {
+                          string s,
+                          #config
+                          childrens {
+                              #ts
+                          }
+                      }
+                  })
+              };
             }
             #vis_t
         });
     }
 }
 
+impl Component {
+    /// Checks the template syntax of the body, which is otherwise only parsed where the component
+    /// is called.
+    fn check_body(&self) -> syn::Result<()> {
+        self.parse_body()?;
+        Ok(())
+    }
+
+    /// Parses the body with placeholders for the props.
+    fn parse_body(&self) -> syn::Result<crate::anon::Anon> {
+        let ts = placeholder_substitutions(self.ts.clone());
+        let config = self.config.anon_arg();
+        crate::anon::Anon::parse.parse2(quote! {
+            #config
+            childrens {
+                #ts
+            }
+        })
+    }
+
+    /// Tokens rendering the component, `escape` being the escape context of the caller.
+    fn render_ts(&self, escape: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        let ts = &self.ts;
+        if !self.typed {
+            #[cfg(feature = "hot_reload")]
+            if let Some(hot_reload) = &self.hot_reload {
+                return self.hot_reload_ts(hot_reload, &escape);
+            }
+            return ts.clone();
+        }
+        let generics = &self.generics;
+        let where_clause = &self.where_clause;
+        let params = self.props.iter().map(|(prop, Prop { ty, .. })| match ty {
+            Some(ty) => quote! { #prop: #ty },
+            // Recursive calls would otherwise instantiate the function with ever nested closures.
+            None if self.recursive => quote! { #prop: &dyn ::origami_engine::Component },
+            None => quote! { #prop: impl ::origami_engine::Component },
+        });
+        let config = self.config.anon_arg();
+        let args = self.props.iter().map(|(prop, Prop { ty, .. })| match ty {
+            Some(_) => typed_arg(quote! { $($#prop)* }),
+            None => slot_arg(quote! { $($#prop)* }, &escape, &config, self.recursive),
+        });
+        quote! {
+            @{
+                #[allow(clippy::too_many_arguments)]
+                fn __origami_render #generics (
+                    #[allow(unused_mut)] mut s: &mut ::origami_engine::Buffer,
+                    #(#params),*
+                ) #where_clause {
+                    ::origami_engine::anon! {
+                        string s,
+                        #config
+                        childrens #escape {
+                            #ts
+                        }
+                    }
+                }
+                ::origami_engine::FnComponent(|s: &mut ::origami_engine::Buffer| {
+                    __origami_render(s, #(#args),*)
+                })
+            };
+        }
+    }
+
+    fn prop_pattern(
+        &self,
+        prop: &Ident,
+        comma: proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        if let Some(count) = self.slots.get(prop) {
+            let params = (0..*count).map(|i| slot_param(prop, i));
+            return quote! {
+                #comma #prop |#($#params:ident),*| {$($#prop:tt)*}
+            };
+        }
+        if let Some(Prop {
+            fallback: Some(_), ..
+        }) = self.props.get(prop)
+        {
+            return quote! {
+                $(#comma #prop {$($#prop:tt)*})?
+            };
+        }
+        quote! {
+            #comma #prop {$($#prop:tt)*}
+        }
+    }
+}
+
+fn typed_arg(ts: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    quote! {
+        ::origami_engine::__prop_expr!(#ts)
+    }
+}
+
+fn slot_arg(
+    ts: proc_macro2::TokenStream,
+    escape: &proc_macro2::TokenStream,
+    config: &proc_macro2::TokenStream,
+    recursive: bool,
+) -> proc_macro2::TokenStream {
+    let reference = recursive.then(|| quote! { & });
+    quote! {
+        #reference ::origami_engine::FnComponent(|#[allow(unused_mut)] mut s: &mut ::origami_engine::Buffer| {
+            ::origami_engine::anon! {
+                string s,
+                #config
+                childrens #escape {
+                    #ts
+                }
+            }
+        })
+    }
+}
+
+fn slot_param(prop: &Ident, i: usize) -> Ident {
+    format_ident!("{}__{}", prop, i)
+}
+
 #[derive(Debug)]
 enum Next {
     Ident,
     Any,
 }
 
-fn macro_rep(ps: ParseStream, props: &IndexSet<Ident>) -> syn::Result<proc_macro2::TokenStream> {
-    let mut ts = proc_macro2::TokenStream::new();
-    while !ps.is_empty() {
-        ts.extend(ps.parse::<TokenTree>()?.into_token_stream());
+/// Replaces `crate` with `$crate` in exported components, in any kind of group.
+fn dollar_crate(ts: proc_macro2::TokenStream, export: bool) -> proc_macro2::TokenStream {
+    if !export {
+        return ts;
     }
+    ts.into_iter()
+        .map(|token| match token {
+            TokenTree::Ident(ident) if ident == "crate" => quote! { $#ident },
+            TokenTree::Group(group) => {
+                let mut n_group =
+                    Group::new(group.delimiter(), dollar_crate(group.stream(), export));
+                n_group.set_span(group.span());
+                n_group.into_token_stream()
+            }
+            token => token.into_token_stream(),
+        })
+        .collect()
+}
+
+fn macro_rep(
+    ts: proc_macro2::TokenStream,
+    props: &IndexMap<Ident, Prop>,
+    export: bool,
+    scoped: bool,
+    recursive: Option<(&Ident, &IndexMap<Ident, Prop>)>,
+    config: &Config,
+    warnings: &mut proc_macro2::TokenStream,
+) -> syn::Result<(proc_macro2::TokenStream, IndexMap<Ident, usize>)> {
+    let mut locals = IndexSet::new();
+    bound_idents(ts.clone(), &mut locals);
+    let mut rep = MacroRep::new(props, export, scoped, recursive, locals, config, warnings);
     let mut next = Next::Any;
-    let ts = handle_token(&mut next, ts, props)?;
-    Ok(ts)
-}
-
-fn handle_token(
-    next: &mut Next,
-    o_ts: proc_macro2::TokenStream,
-    props: &IndexSet<Ident>,
-) -> syn::Result<proc_macro2::TokenStream> {
-    let mut ts = proc_macro2::TokenStream::new();
-    let mut o_ts_i = o_ts.into_iter();
-    while let Some(token) = o_ts_i.next() {
-        match (&next, &token) {
-            (Next::Any, TokenTree::Group(group)) => {
-                if let Delimiter::Brace = group.delimiter() {
-                    let rts = handle_token(next, group.stream(), props)?;
+    let ts = rep.handle_token(&mut next, ts)?;
+    let unused = props
+        .keys()
+        .filter(|prop| !rep.uses.contains_key(*prop) && !rep.checked.contains(*prop))
+        .collect::<Vec<_>>();
+    for prop in unused {
+        rep.lint(
+            config.unused_props,
+            prop,
+            format!("Prop `{}` is declared but never used", prop),
+        )?;
+    }
+    let slots = rep
+        .uses
+        .into_iter()
+        .filter_map(|(prop, prop_use)| match prop_use {
+            PropUse::Slot(count) => Some((prop, count)),
+            PropUse::Tokens => None,
+        })
+        .collect();
+    Ok((ts, slots))
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum PropUse {
+    Tokens,
+    Slot(usize),
+}
+
+struct MacroRep<'a> {
+    props: &'a IndexMap<Ident, Prop>,
+    export: bool,
+    /// Whether the component has `style scoped`, which must not apply to markup of the caller.
+    scoped: bool,
+    /// Name and props of a recursive component, whose calls to itself use the generated function.
+    recursive: Option<(&'a Ident, &'a IndexMap<Ident, Prop>)>,
+    /// Idents bound by `for` and `match` patterns of the body.
+    locals: IndexSet<Ident>,
+    /// Configuration of the crate, deciding the level of the lints.
+    config: &'a Config,
+    warnings: &'a mut proc_macro2::TokenStream,
+    uses: IndexMap<Ident, PropUse>,
+    /// Props checked with `has_slot`.
+    checked: IndexSet<Ident>,
+    /// Props whose fallback markup is currently being expanded.
+    fallbacks: Vec<Ident>,
+    /// Local templates defined with `def`, one scope per brace group.
+    defs: Vec<IndexMap<Ident, Def>>,
+    /// Local templates currently being expanded.
+    expanding: Vec<Ident>,
+}
+
+struct Def {
+    params: Vec<Ident>,
+    body: proc_macro2::TokenStream,
+}
+
+impl<'a> MacroRep<'a> {
+    fn new(
+        props: &'a IndexMap<Ident, Prop>,
+        export: bool,
+        scoped: bool,
+        recursive: Option<(&'a Ident, &'a IndexMap<Ident, Prop>)>,
+        locals: IndexSet<Ident>,
+        config: &'a Config,
+        warnings: &'a mut proc_macro2::TokenStream,
+    ) -> Self {
+        Self {
+            props,
+            export,
+            scoped,
+            recursive,
+            locals,
+            config,
+            warnings,
+            uses: IndexMap::new(),
+            checked: IndexSet::new(),
+            fallbacks: Vec::new(),
+            defs: Vec::new(),
+            expanding: Vec::new(),
+        }
+    }
+
+    /// Reports `message` at `ident` as an error or a warning, depending on `level`.
+    fn lint(&mut self, level: Level, ident: &Ident, message: String) -> syn::Result<()> {
+        match level {
+            Level::Allow => {}
+            Level::Warn => self.warnings.extend(warning(ident.span(), &message)),
+            Level::Deny => bail!(ident, message),
+        }
+        Ok(())
+    }
+
+    fn use_prop(&mut self, prop: &Ident, prop_use: PropUse) -> syn::Result<()> {
+        match self.uses.get(prop) {
+            Some(prev) if *prev != prop_use => {
+                bail!(
+                    prop,
+                    format!(
+                        "Prop `{}` must be used either as `@{};` or as a scoped slot with the same number of arguments everywhere",
+                        prop, prop
+                    )
+                );
+            }
+            Some(_) => {}
+            None => {
+                self.uses.insert(prop.clone(), prop_use);
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_token(
+        &mut self,
+        next: &mut Next,
+        o_ts: proc_macro2::TokenStream,
+    ) -> syn::Result<proc_macro2::TokenStream> {
+        let mut ts = proc_macro2::TokenStream::new();
+        self.defs.push(collect_defs(o_ts.clone())?);
+        let mut o_ts_i = o_ts.into_iter().peekable();
+        while let Some(token) = o_ts_i.next() {
+            match (&next, &token) {
+                (Next::Any, TokenTree::Group(group)) => {
+                    if let Delimiter::Brace = group.delimiter() {
+                        let rts = self.handle_token(next, group.stream())?;
+                        let mut new_group = Group::new(Delimiter::Brace, rts);
+                        new_group.set_span(group.span());
+                        ts.extend(Some(TokenTree::Group(new_group)));
+                        continue;
+                    } else {
+                        ts.extend(dollar_crate(token.into_token_stream(), self.export));
+                    }
+                }
+                (Next::Any, TokenTree::Punct(p)) if p.as_char() == '@' => {
+                    *next = Next::Ident;
+                }
+                (Next::Any, TokenTree::Ident(ident)) if self.export && ident == "crate" => {
                     ts.extend(quote! {
+                        $#ident
+                    });
+                }
+                (Next::Any, TokenTree::Ident(ident))
+                    if ident == "call" && self.is_recursive_call(o_ts_i.clone()) =>
+                {
+                    o_ts_i.next();
+                    let Some(TokenTree::Group(group)) = o_ts_i.next() else {
+                        unreachable!()
+                    };
+                    ts.extend(self.extend_recursive_call(group)?);
+                }
+                (Next::Any, TokenTree::Ident(ident))
+                    if ident == "def" && is_def(o_ts_i.clone()) =>
+                {
+                    // Already collected, expanded where it is called.
+                    o_ts_i.nth(2);
+                }
+                (Next::Any, TokenTree::Ident(ident))
+                    if self.find_def(ident).is_some()
+                        && matches!(o_ts_i.peek(), Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis) =>
+                {
+                    let Some(TokenTree::Group(group)) = o_ts_i.next() else {
+                        unreachable!()
+                    };
+                    if matches!(o_ts_i.peek(), Some(TokenTree::Punct(p)) if p.as_char() == ';') {
+                        o_ts_i.next();
+                    }
+                    ts.extend(self.extend_def(ident, group.stream())?);
+                }
+                (Next::Any, TokenTree::Punct(p))
+                    if p.as_char() == '#'
+                        && matches!(o_ts_i.peek(), Some(TokenTree::Ident(ident)) if ident == "extends") =>
+                {
+                    ts.extend(token.into_token_stream());
+                    for token in o_ts_i.by_ref() {
+                        let TokenTree::Group(overrides) = token else {
+                            ts.extend(dollar_crate(token.into_token_stream(), self.export));
+                            continue;
+                        };
+                        let mut rts = proc_macro2::TokenStream::new();
+                        for token in overrides.stream() {
+                            match token {
+                                TokenTree::Group(group) => {
+                                    let body = self.handle_token(&mut Next::Any, group.stream())?;
+                                    rts.extend(quote! {
+                                        { #body }
+                                    });
+                                }
+                                token => rts.extend(token.into_token_stream()),
+                            }
+                        }
+                        ts.extend(quote! {
+                            { #rts }
+                        });
+                        break;
+                    }
+                }
+                (Next::Any, TokenTree::Ident(ident))
+                    if ident == "block" && self.is_block(o_ts_i.clone()) =>
+                {
+                    let Some(TokenTree::Ident(block)) = o_ts_i.next() else {
+                        unreachable!()
+                    };
+                    o_ts_i.next();
+                    self.use_prop(&block, PropUse::Tokens)?;
+                    ts.extend(self.extend_prop(&block)?);
+                }
+                (Next::Any, TokenTree::Ident(ident)) if ident == "has_slot" => {
+                    match o_ts_i.peek() {
+                        Some(TokenTree::Group(group))
+                            if group.delimiter() == Delimiter::Parenthesis =>
                         {
-                            #rts
+                            let prop: Ident = syn::parse2(group.stream())?;
+                            if !self.props.contains_key(&prop) {
+                                bail!(prop, format!("Unknown prop `{}`", prop));
+                            }
+                            o_ts_i.next();
+                            let prop_ts = self.prop_tokens(&prop);
+                            self.checked.insert(prop);
+                            ts.extend(quote! {
+                                ::origami_engine::__has_slot!(#prop_ts)
+                            });
                         }
-                    });
-                    continue;
-                } else {
+                        _ => ts.extend(token.into_token_stream()),
+                    }
+                }
+                (Next::Ident, _) => {
+                    let n_token = o_ts_i.next();
+                    match (&token, &n_token) {
+                        (TokenTree::Ident(ident), Some(TokenTree::Punct(n_t)))
+                            if self.props.contains_key(ident) && n_t.as_char() == ';' =>
+                        {
+                            self.use_prop(ident, PropUse::Tokens)?;
+                            ts.extend(self.extend_prop(ident)?);
+                        }
+                        (TokenTree::Ident(ident), Some(TokenTree::Group(group)))
+                            if self.props.contains_key(ident)
+                                && group.delimiter() == Delimiter::Parenthesis
+                                && matches!(o_ts_i.peek(), Some(TokenTree::Punct(p)) if p.as_char() == ';') =>
+                        {
+                            o_ts_i.next();
+                            ts.extend(self.extend_slot(ident, group.stream())?);
+                        }
+                        (TokenTree::Ident(ident), Some(TokenTree::Punct(n_t)))
+                            if n_t.as_char() == ';' && !self.locals.contains(ident) =>
+                        {
+                            if let Some(prop) = self.similar_prop(ident) {
+                                let message = format!(
+                                    "Unknown prop `{}`, did you mean `{}`? Use `@({});` to render a variable from the enclosing scope",
+                                    ident, prop, ident
+                                );
+                                self.lint(self.config.similar_props, ident, message)?;
+                            }
+                            ts.extend(quote! {
+                                @#token #n_token
+                            });
+                        }
+                        _ => {
+                            let token = dollar_crate(token.into_token_stream(), self.export);
+                            let n_token = dollar_crate(n_token.into_token_stream(), self.export);
+                            ts.extend(quote! {
+                                @#token #n_token
+                            });
+                        }
+                    }
+                    *next = Next::Any;
+                }
+                _ => {
                     ts.extend(token.into_token_stream());
                 }
             }
-            (Next::Any, TokenTree::Punct(p)) if p.as_char() == '@' => {
-                *next = Next::Ident;
+        }
+        self.defs.pop();
+        Ok(ts)
+    }
+
+    fn is_block(&self, mut o_ts_i: impl Iterator<Item = TokenTree>) -> bool {
+        matches!(
+            (o_ts_i.next(), o_ts_i.next()),
+            (Some(TokenTree::Ident(block)), Some(TokenTree::Group(group)))
+                if self.props.contains_key(&block) && group.delimiter() == Delimiter::Brace
+        )
+    }
+
+    fn find_def(&self, name: &Ident) -> Option<&Def> {
+        self.defs.iter().rev().find_map(|defs| defs.get(name))
+    }
+
+    fn extend_def(
+        &mut self,
+        name: &Ident,
+        args: proc_macro2::TokenStream,
+    ) -> syn::Result<proc_macro2::TokenStream> {
+        if self.expanding.contains(name) {
+            bail!(
+                name,
+                format!("Local template `{}` cannot call itself", name)
+            );
+        }
+        let Some(Def { params, body }) = self.find_def(name) else {
+            unreachable!()
+        };
+        let (params, body) = (params.clone(), body.clone());
+        if let Some(param) = params.iter().find(|param| self.props.contains_key(*param)) {
+            bail!(
+                param,
+                format!(
+                    "Parameter `{}` of local template `{}` shadows a prop",
+                    param, name
+                )
+            );
+        }
+        let args = Punctuated::<Expr, Token![,]>::parse_terminated.parse2(args)?;
+        if args.len() != params.len() {
+            bail!(
+                name,
+                format!(
+                    "Local template `{}` expects {} arguments, found {}",
+                    name,
+                    params.len(),
+                    args.len()
+                )
+            );
+        }
+        let args = args
+            .into_iter()
+            .map(|arg| self.handle_token(&mut Next::Any, arg.into_token_stream()))
+            .collect::<syn::Result<Vec<_>>>()?;
+        self.expanding.push(name.clone());
+        let body = self.handle_token(&mut Next::Any, body)?;
+        self.expanding.pop();
+        Ok(quote! {
+            match (#(#args,)*); {
+                (#(#params,)*) => {
+                    #body
+                }
+            }
+        })
+    }
+
+    fn similar_prop(&self, ident: &Ident) -> Option<&Ident> {
+        let ident = ident.to_string();
+        self.props.keys().find(|prop| {
+            let prop = prop.to_string();
+            if prop.eq_ignore_ascii_case(&ident) {
+                return true;
+            }
+            // Short names like `a` and `b` are too close to each other to be typos.
+            if prop.len().min(ident.len()) < 4 {
+                return false;
+            }
+            let max = if prop.len() > 4 { 2 } else { 1 };
+            edit_distance(&prop, &ident) <= max
+        })
+    }
+
+    fn prop_tokens(&self, prop: &Ident) -> proc_macro2::TokenStream {
+        if let Some(Prop {
+            fallback: Some(_), ..
+        }) = self.props.get(prop)
+        {
+            quote! {
+                $($($#prop)*)?
+            }
+        } else {
+            quote! {
+                $($#prop)*
+            }
+        }
+    }
+
+    /// Wraps markup of the caller so the scope of the component is not applied to it.
+    fn unscoped(&self, ts: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        if self.scoped {
+            let sigil = Punct::new('#', Spacing::Alone);
+            quote! {
+                #sigil scope none {
+                    #ts
+                }
+            }
+        } else {
+            ts
+        }
+    }
+
+    fn extend_prop(&mut self, prop: &Ident) -> syn::Result<proc_macro2::TokenStream> {
+        let prop_ts = self.prop_tokens(prop);
+        let Some(Prop {
+            fallback: Some(fallback),
+            ..
+        }) = self.props.get(prop)
+        else {
+            return Ok(self.unscoped(prop_ts));
+        };
+        if self.fallbacks.contains(prop) {
+            bail!(
+                prop,
+                format!("Fallback of prop `{}` cannot render itself", prop)
+            );
+        }
+        self.fallbacks.push(prop.clone());
+        let fallback = self.handle_token(&mut Next::Any, fallback.clone())?;
+        self.fallbacks.pop();
+        let unscoped_ts = self.unscoped(prop_ts.clone());
+        Ok(quote! {
+            if ::origami_engine::__has_slot!(#prop_ts); {
+                #unscoped_ts
+            } else {
+                #fallback
+            }
+        })
+    }
+
+    fn is_recursive_call(&self, mut o_ts_i: impl Iterator<Item = TokenTree>) -> bool {
+        let Some((name, _)) = self.recursive else {
+            return false;
+        };
+        matches!(
+            (o_ts_i.next(), o_ts_i.next()),
+            (Some(TokenTree::Ident(ident)), Some(TokenTree::Group(group)))
+                if ident == *name && group.delimiter() == Delimiter::Brace
+        )
+    }
+
+    fn extend_recursive_call(
+        &mut self,
+        group: proc_macro2::Group,
+    ) -> syn::Result<proc_macro2::TokenStream> {
+        let Some((_, props)) = self.recursive else {
+            unreachable!()
+        };
+        let mut passed = Punctuated::<RecursiveProp, Token![,]>::parse_terminated
+            .parse2(group.stream())?
+            .into_iter()
+            .map(|prop| (prop.ident, prop.ts))
+            .collect::<IndexMap<_, _>>();
+        let mut args = Vec::new();
+        for (prop, Prop { ty, .. }) in props {
+            let Some(ts) = passed.shift_remove(prop) else {
+                bail!(group, format!("Missing prop `{}`", prop));
+            };
+            let ts = dollar_crate(ts, self.export);
+            args.push(match ty {
+                Some(_) => typed_arg(ts),
+                None => slot_arg(ts, &quote! {}, &self.config.anon_arg(), true),
+            });
+        }
+        if let Some((prop, _)) = passed.first() {
+            bail!(prop, format!("Unknown prop `{}`", prop));
+        }
+        Ok(quote! {
+            @::origami_engine::FnComponent(|s: &mut ::origami_engine::Buffer| {
+                __origami_render(s, #(#args),*)
+            });
+        })
+    }
+
+    fn extend_slot(
+        &mut self,
+        prop: &Ident,
+        args: proc_macro2::TokenStream,
+    ) -> syn::Result<proc_macro2::TokenStream> {
+        if let Some(Prop {
+            fallback: Some(_), ..
+        }) = self.props.get(prop)
+        {
+            bail!(
+                prop,
+                format!("Scoped slot `{}` cannot have fallback content", prop)
+            );
+        }
+        let args = Punctuated::<Expr, Token![,]>::parse_terminated.parse2(args)?;
+        self.use_prop(prop, PropUse::Slot(args.len()))?;
+        let params = (0..args.len()).map(|i| slot_param(prop, i));
+        let args = args
+            .into_iter()
+            .map(|arg| self.handle_token(&mut Next::Any, arg.into_token_stream()))
+            .collect::<syn::Result<Vec<_>>>()?;
+        let prop_ts = self.unscoped(quote! { $($#prop)* });
+        Ok(quote! {
+            match (#(#args,)*); {
+                (#($#params,)*) => {
+                    #prop_ts
+                }
+            }
+        })
+    }
+}
+
+/// A prop passed in a call of a recursive component to itself.
+struct RecursiveProp {
+    ident: Ident,
+    ts: proc_macro2::TokenStream,
+}
+
+impl Parse for RecursiveProp {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let ident = input.parse()?;
+        let content;
+        braced!(content in input);
+        Ok(Self {
+            ident,
+            ts: content.parse()?,
+        })
+    }
+}
+
+/// Collects the idents bound by `for` patterns and `match` arm patterns, which can shadow props.
+fn bound_idents(ts: proc_macro2::TokenStream, locals: &mut IndexSet<Ident>) {
+    let mut ts_i = ts.into_iter();
+    while let Some(token) = ts_i.next() {
+        match token {
+            TokenTree::Ident(ident) if ident == "for" => {
+                for token in ts_i.by_ref() {
+                    match token {
+                        TokenTree::Ident(ident) if ident == "in" => break,
+                        token => pattern_idents(token, locals),
+                    }
+                }
+            }
+            TokenTree::Ident(ident) if ident == "match" => {
+                let Some(TokenTree::Group(arms)) = ts_i
+                    .by_ref()
+                    .skip_while(|token| !matches!(token, TokenTree::Punct(p) if p.as_char() == ';'))
+                    .nth(1)
+                else {
+                    continue;
+                };
+                let mut arms_i = arms.stream().into_iter().peekable();
+                while let Some(token) = arms_i.next() {
+                    match token {
+                        TokenTree::Punct(p) if p.as_char() == '=' => {
+                            if matches!(arms_i.peek(), Some(TokenTree::Punct(p)) if p.as_char() == '>')
+                            {
+                                arms_i.next();
+                                if let Some(TokenTree::Group(body)) = arms_i.next() {
+                                    bound_idents(body.stream(), locals);
+                                }
+                            }
+                        }
+                        token => pattern_idents(token, locals),
+                    }
+                }
+            }
+            TokenTree::Ident(ident) if ident == "def" => {
+                if let (Some(_), Some(params)) = (ts_i.next(), ts_i.next()) {
+                    pattern_idents(params, locals);
+                }
+            }
+            TokenTree::Group(group) => bound_idents(group.stream(), locals),
+            _ => {}
+        }
+    }
+}
+
+fn pattern_idents(token: TokenTree, locals: &mut IndexSet<Ident>) {
+    match token {
+        TokenTree::Ident(ident) => {
+            locals.insert(ident);
+        }
+        TokenTree::Group(group) => {
+            for token in group.stream() {
+                pattern_idents(token, locals);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            cur.push((prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1));
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+fn is_def(mut ts_i: impl Iterator<Item = TokenTree>) -> bool {
+    matches!(
+        (ts_i.next(), ts_i.next(), ts_i.next()),
+        (
+            Some(TokenTree::Ident(_)),
+            Some(TokenTree::Group(params)),
+            Some(TokenTree::Group(body)),
+        ) if params.delimiter() == Delimiter::Parenthesis && body.delimiter() == Delimiter::Brace
+    )
+}
+
+/// Collects the local templates defined with `def name(params) { body }` directly in `ts`.
+fn collect_defs(ts: proc_macro2::TokenStream) -> syn::Result<IndexMap<Ident, Def>> {
+    let mut defs = IndexMap::new();
+    let mut ts_i = ts.into_iter();
+    while let Some(token) = ts_i.next() {
+        let TokenTree::Ident(ident) = token else {
+            continue;
+        };
+        if ident != "def" || !is_def(ts_i.clone()) {
+            continue;
+        }
+        let (
+            Some(TokenTree::Ident(name)),
+            Some(TokenTree::Group(params)),
+            Some(TokenTree::Group(body)),
+        ) = (ts_i.next(), ts_i.next(), ts_i.next())
+        else {
+            unreachable!()
+        };
+        let params = Punctuated::<Ident, Token![,]>::parse_terminated
+            .parse2(params.stream())?
+            .into_iter()
+            .collect();
+        if defs.contains_key(&name) {
+            bail!(
+                name,
+                format!("Local template `{}` is already defined", name)
+            );
+        }
+        defs.insert(
+            name,
+            Def {
+                params,
+                body: body.stream(),
+            },
+        );
+    }
+    Ok(defs)
+}
+
+/// Collects the regions declared with `block name { default }`, which become optional props.
+fn collect_blocks(
+    ts: proc_macro2::TokenStream,
+    blocks: &mut IndexMap<Ident, proc_macro2::TokenStream>,
+) -> syn::Result<()> {
+    let mut ts_i = ts.into_iter();
+    while let Some(token) = ts_i.next() {
+        match token {
+            TokenTree::Ident(ident) if ident == "block" => {
+                let mut n_ts_i = ts_i.clone();
+                let (Some(TokenTree::Ident(block)), Some(TokenTree::Group(group))) =
+                    (n_ts_i.next(), n_ts_i.next())
+                else {
+                    continue;
+                };
+                if group.delimiter() != Delimiter::Brace {
+                    continue;
+                }
+                ts_i = n_ts_i;
+                if blocks.contains_key(&block) {
+                    bail!(block, format!("Block `{}` is declared twice", block));
+                }
+                blocks.insert(block, group.stream());
+                collect_blocks(group.stream(), blocks)?;
+            }
+            TokenTree::Punct(p)
+                if p.as_char() == '#'
+                    && matches!(ts_i.clone().next(), Some(TokenTree::Ident(ident)) if ident == "extends") =>
+            {
+                // Blocks passed to the extended component are overrides, not declarations.
+                let Some(TokenTree::Group(overrides)) = ts_i
+                    .by_ref()
+                    .find(|token| matches!(token, TokenTree::Group(_)))
+                else {
+                    continue;
+                };
+                for token in overrides.stream() {
+                    if let TokenTree::Group(group) = token {
+                        collect_blocks(group.stream(), blocks)?;
+                    }
+                }
             }
-            (Next::Ident, _) => {
-                let n_token = o_ts_i.next();
-                match (&token, &n_token) {
-                    (TokenTree::Ident(ident), Some(TokenTree::Punct(n_t)))
-                        if props.contains(ident) && n_t.as_char() == ';' =>
+            TokenTree::Group(group) => collect_blocks(group.stream(), blocks)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn has_scoped_style(ts: proc_macro2::TokenStream) -> bool {
+    let mut ts_i = ts.into_iter().peekable();
+    while let Some(token) = ts_i.next() {
+        match token {
+            TokenTree::Ident(ident)
+                if ident == "style"
+                    && matches!(ts_i.peek(), Some(TokenTree::Ident(ident)) if ident == "scoped") =>
+            {
+                return true;
+            }
+            TokenTree::Group(group) if has_scoped_style(group.stream()) => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Replaces the prop substitutions of `macro_rules!` in `ts` with `""`, which is valid template
+/// syntax wherever a prop can be used, i.e. as a text, an expression or an attribute value.
+fn placeholder_substitutions(ts: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let mut replaced = proc_macro2::TokenStream::new();
+    let mut ts_i = ts.into_iter().peekable();
+    while let Some(token) = ts_i.next() {
+        match token {
+            TokenTree::Punct(p) if p.as_char() == '$' => {
+                if let Some(TokenTree::Group(group)) = ts_i.peek() {
+                    let mut placeholder = proc_macro2::Literal::string("");
+                    placeholder.set_span(group.span());
+                    replaced.extend(Some(TokenTree::Literal(placeholder)));
+                    ts_i.next();
+                    if matches!(ts_i.peek(), Some(TokenTree::Punct(p)) if matches!(p.as_char(), '*' | '?'))
                     {
-                        ts.extend(quote! {
-                            $($#ident)*
-                        });
+                        ts_i.next();
                     }
-                    _ => ts.extend(quote! {
-                        @#token #n_token
-                    }),
                 }
-                *next = Next::Any;
             }
-            _ => {
-                ts.extend(token.into_token_stream());
+            TokenTree::Group(group) => {
+                let mut new_group =
+                    Group::new(group.delimiter(), placeholder_substitutions(group.stream()));
+                new_group.set_span(group.span());
+                replaced.extend(Some(TokenTree::Group(new_group)));
             }
+            token => replaced.extend(Some(token)),
         }
     }
-    Ok(ts)
+    replaced
 }
@@ -0,0 +1,506 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[cfg(feature = "minify_html")]
+use minify_html::Cfg;
+#[cfg(feature = "minify_html")]
+use origami_syntax::{kw::minify, MinifyOptions};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::parse::{Parse, ParseStream, Parser};
+use syn::punctuated::Punctuated;
+#[cfg(feature = "minify_html")]
+use syn::token::Paren;
+use syn::{Ident, Lit, Token};
+use toml::de::{DeTable, DeValue};
+use toml::Spanned;
+
+/// Configuration of the crate using the macros, read from `origami.toml` next to its
+/// `Cargo.toml`, and overridden by components with `#[origami(..)]`.
+#[derive(Clone)]
+pub struct Config {
+    /// Path of the file, if there is one.
+    path: Option<String>,
+    /// Whether text and expressions are escaped where neither `escape` nor `noescape` is set.
+    #[cfg_attr(not(feature = "html_escape"), allow(dead_code))]
+    pub escape: bool,
+    /// Whether elements without content end with `/>`, or with `>`.
+    pub self_closing_slash: bool,
+    /// Whether `script`, `style` and `include` are minified without `minify(..)`.
+    #[cfg_attr(not(feature = "minify_html"), allow(dead_code))]
+    pub minify: bool,
+    /// Whether the static markup is minified, with the `minify_document` feature.
+    #[cfg_attr(not(feature = "minify_document"), allow(dead_code))]
+    pub minify_document: bool,
+    /// Level of props that are never used.
+    pub unused_props: Level,
+    /// Level of variables rendered with the name of a prop.
+    pub similar_props: Level,
+    /// Options of the minifier from the `[minify]` table, applied before those of blocks.
+    #[cfg(feature = "minify_html")]
+    minify_options: Vec<(String, bool)>,
+    /// Settings of `#[origami(..)]`, passed on to the `anon!` calls of the component.
+    overrides: Vec<TokenStream>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            path: None,
+            escape: true,
+            self_closing_slash: true,
+            minify: true,
+            minify_document: true,
+            unused_props: Level::Warn,
+            similar_props: Level::Warn,
+            #[cfg(feature = "minify_html")]
+            minify_options: Vec::new(),
+            overrides: Vec::new(),
+        }
+    }
+}
+
+/// Settings of the `[defaults]` table.
+const DEFAULTS: &[&str] = &["escape", "minify", "minify_document", "self_closing"];
+/// Settings of the `[lints]` table.
+const LINTS: &[&str] = &["similar_props", "unused_props"];
+
+/// Level of a lint of the `[lints]` table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+/// Value of a setting, a TOML value or a literal of `#[origami(..)]`.
+enum Setting {
+    Bool(bool),
+    Str(String),
+    Other,
+}
+
+impl From<&DeValue<'_>> for Setting {
+    fn from(value: &DeValue) -> Self {
+        match value {
+            DeValue::Boolean(value) => Self::Bool(*value),
+            DeValue::String(value) => Self::Str(value.to_string()),
+            _ => Self::Other,
+        }
+    }
+}
+
+impl From<&Lit> for Setting {
+    fn from(lit: &Lit) -> Self {
+        match lit {
+            Lit::Bool(value) => Self::Bool(value.value),
+            Lit::Str(value) => Self::Str(value.value()),
+            _ => Self::Other,
+        }
+    }
+}
+
+/// Modification time and length of `origami.toml`, `None` if there is no file.
+type Stamp = Option<(SystemTime, u64)>;
+
+/// Configurations by manifest directory, parsed again when the file changes. They have no
+/// overrides, tokens cannot outlive the expansion creating them.
+type Cache = HashMap<PathBuf, (Stamp, Result<Config, String>)>;
+
+thread_local! {
+    static CONFIGS: RefCell<Cache> = RefCell::default();
+}
+
+impl Config {
+    pub fn load() -> syn::Result<Self> {
+        let Ok(manifest_dir) = std::env::var("CARGO_MANIFEST_DIR") else {
+            return Ok(Self::default());
+        };
+        let path = Path::new(&manifest_dir).join("origami.toml");
+        let stamp = std::fs::metadata(&path)
+            .ok()
+            .and_then(|metadata| Some((metadata.modified().ok()?, metadata.len())));
+        CONFIGS.with_borrow_mut(|configs| {
+            let (cached, config) = configs
+                .entry(path.clone())
+                .or_insert_with(|| (stamp, Self::read(&path)));
+            if *cached != stamp {
+                *cached = stamp;
+                *config = Self::read(&path);
+            }
+            config.clone().map_err(error)
+        })
+    }
+
+    fn read(path: &Path) -> Result<Self, String> {
+        let Ok(text) = std::fs::read_to_string(path) else {
+            return Ok(Self::default());
+        };
+        let path = path.to_string_lossy();
+        let mut config = Self::parse(&File {
+            path: &path,
+            text: &text,
+        })?;
+        config.path = Some(path.into_owned());
+        Ok(config)
+    }
+
+    fn parse(file: &File) -> Result<Self, String> {
+        let table = DeTable::parse(file.text).map_err(|err| {
+            file.error(
+                err.span().unwrap_or_default(),
+                format!("Invalid TOML: {}", err.message()),
+            )
+        })?;
+        let mut config = Self::default();
+        for (key, value) in table.into_inner() {
+            let (key_span, key) = (key.span(), key.into_inner());
+            let names = match key.as_ref() {
+                "defaults" => DEFAULTS,
+                "lints" => LINTS,
+                "minify" => {
+                    config.load_minify(file, value)?;
+                    continue;
+                }
+                _ => return Err(file.error(key_span, format!("Unknown key `{}`", key))),
+            };
+            for (name, value) in file.table(&key, value)? {
+                let (name_span, name) = (name.span(), name.into_inner());
+                if !names.contains(&name.as_ref()) {
+                    return Err(
+                        file.error(name_span, format!("Unknown setting `{}.{}`", key, name))
+                    );
+                }
+                config
+                    .set(&name, value.get_ref().into())
+                    .map_err(|message| {
+                        file.error(value.span(), format!("`{}.{}`: {}", key, name, message))
+                    })?;
+            }
+        }
+        Ok(config)
+    }
+
+    #[allow(unused_variables)]
+    fn load_minify(&mut self, file: &File, value: Spanned<DeValue>) -> Result<(), String> {
+        let table = file.table("minify", value)?;
+        #[cfg(feature = "minify_html")]
+        for (name, value) in table {
+            let (name_span, name) = (name.span(), name.into_inner());
+            let DeValue::Boolean(flag) = *value.get_ref() else {
+                return Err(
+                    file.error(value.span(), format!("`minify.{}` must be a boolean", name))
+                );
+            };
+            if !origami_syntax::set_minify_option(&mut Cfg::default(), &name, flag) {
+                return Err(file.error(name_span, format!("Unknown minify option `{}`", name)));
+            }
+            self.minify_options.push((name.into_owned(), flag));
+        }
+        Ok(())
+    }
+
+    /// Applies the settings of `#[origami(..)]`, e.g. `escape = false, unused_props = "allow"`.
+    /// `minify(..)` replaces the options of the `[minify]` table.
+    pub fn apply_overrides(&mut self, ts: TokenStream) -> syn::Result<()> {
+        for setting in Punctuated::<Override, Token![,]>::parse_terminated.parse2(ts.clone())? {
+            let (name, value) = match setting {
+                Override::Setting { name, value } => (name, value),
+                #[cfg(feature = "minify_html")]
+                Override::Minify(options) => {
+                    self.minify_options = options
+                        .0
+                        .into_iter()
+                        .map(|(name, value)| (name.to_string(), value))
+                        .collect();
+                    continue;
+                }
+            };
+            let key = name.to_string();
+            if !DEFAULTS.contains(&key.as_str()) && !LINTS.contains(&key.as_str()) {
+                return Err(syn::Error::new(
+                    name.span(),
+                    format!("Unknown setting `{}`", name),
+                ));
+            }
+            self.set(&key, (&value).into())
+                .map_err(|message| syn::Error::new(value.span(), message))?;
+        }
+        self.overrides.push(ts);
+        Ok(())
+    }
+
+    /// Replaces the overrides with all the settings `anon!` reads, so that exported components
+    /// render with the configuration of the crate declaring them, not the one calling them.
+    pub fn bake(&mut self) {
+        let escape = self.escape;
+        let minify = self.minify;
+        let minify_document = self.minify_document;
+        let self_closing = if self.self_closing_slash {
+            "slash"
+        } else {
+            "html"
+        };
+        #[allow(unused_mut)]
+        let mut settings = quote! {
+            escape = #escape,
+            minify = #minify,
+            minify_document = #minify_document,
+            self_closing = #self_closing
+        };
+        #[cfg(feature = "minify_html")]
+        {
+            let options = self.minify_options.iter().map(|(name, value)| {
+                let name = Ident::new(name, Span::call_site());
+                quote! { #name = #value }
+            });
+            settings.extend(quote! { , minify(#(#options),*) });
+        }
+        self.overrides = vec![settings];
+    }
+
+    /// The `config { .. }` argument of `anon!` applying the overrides, if there are any.
+    pub fn anon_arg(&self) -> TokenStream {
+        if self.overrides.is_empty() {
+            return TokenStream::new();
+        }
+        let overrides = &self.overrides;
+        quote! {
+            config { #(#overrides,)* },
+        }
+    }
+
+    fn set(&mut self, name: &str, setting: Setting) -> Result<(), String> {
+        match name {
+            "escape" => self.escape = bool_setting(setting)?,
+            "minify" => self.minify = bool_setting(setting)?,
+            "minify_document" => self.minify_document = bool_setting(setting)?,
+            "self_closing" => self.self_closing_slash = choice(setting, "slash", "html")?,
+            "similar_props" => self.similar_props = level(setting)?,
+            "unused_props" => self.unused_props = level(setting)?,
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+
+    /// Sets the options of the `[minify]` table on `cfg`.
+    #[cfg(feature = "minify_html")]
+    pub fn apply_minify(&self, cfg: &mut Cfg) {
+        for (name, value) in &self.minify_options {
+            origami_syntax::set_minify_option(cfg, name, *value);
+        }
+    }
+
+    /// Makes the crate depend on the file, so that it is recompiled when the file changes.
+    pub fn dependency(&self) -> TokenStream {
+        let Some(path) = &self.path else {
+            return TokenStream::new();
+        };
+        quote! {
+            const _: &[u8] = include_bytes!(#path);
+        }
+    }
+}
+
+/// A setting of `#[origami(..)]`.
+enum Override {
+    /// `name = value`.
+    Setting { name: Ident, value: Lit },
+    /// `minify(..)`, the options of the minifier.
+    #[cfg(feature = "minify_html")]
+    Minify(MinifyOptions),
+}
+
+impl Parse for Override {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        #[cfg(feature = "minify_html")]
+        if input.peek(minify) && input.peek2(Paren) {
+            return Ok(Self::Minify(
+                MinifyOptions::parse(input)?.unwrap_or_default(),
+            ));
+        }
+        let name = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let value = input.parse()?;
+        Ok(Self::Setting { name, value })
+    }
+}
+
+fn bool_setting(setting: Setting) -> Result<bool, String> {
+    match setting {
+        Setting::Bool(value) => Ok(value),
+        _ => Err("Expected a boolean".to_owned()),
+    }
+}
+
+/// Returns whether the setting is `yes`, failing if it is neither `yes` nor `no`.
+fn choice(setting: Setting, yes: &str, no: &str) -> Result<bool, String> {
+    match setting {
+        Setting::Str(value) if value == yes => Ok(true),
+        Setting::Str(value) if value == no => Ok(false),
+        _ => Err(format!("Expected `\"{}\"` or `\"{}\"`", yes, no)),
+    }
+}
+
+fn level(setting: Setting) -> Result<Level, String> {
+    match setting {
+        Setting::Str(value) if value == "allow" => Ok(Level::Allow),
+        Setting::Str(value) if value == "warn" => Ok(Level::Warn),
+        Setting::Str(value) if value == "deny" => Ok(Level::Deny),
+        _ => Err("Expected `\"allow\"`, `\"warn\"` or `\"deny\"`".to_owned()),
+    }
+}
+
+/// The `origami.toml` being parsed, locating its errors.
+struct File<'a> {
+    path: &'a str,
+    text: &'a str,
+}
+
+impl File<'_> {
+    /// Formats `message` as `path:line:column: message`, at the start of `span`.
+    fn error(&self, span: Range<usize>, message: impl std::fmt::Display) -> String {
+        let before = &self.text[..span.start.min(self.text.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before
+            .rsplit('\n')
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .count()
+            + 1;
+        format!("{}:{}:{}: {}", self.path, line, column, message)
+    }
+
+    fn table<'i>(&self, key: &str, value: Spanned<DeValue<'i>>) -> Result<DeTable<'i>, String> {
+        let span = value.span();
+        match value.into_inner() {
+            DeValue::Table(table) => Ok(table),
+            _ => Err(self.error(span, format!("`{}` must be a table", key))),
+        }
+    }
+}
+
+fn error(message: impl std::fmt::Display) -> syn::Error {
+    syn::Error::new(Span::call_site(), message)
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::quote;
+
+    use super::{Config, File, Level};
+
+    fn parse(text: &str) -> Result<Config, String> {
+        Config::parse(&File {
+            path: "origami.toml",
+            text,
+        })
+    }
+
+    #[test]
+    fn parse_config() {
+        assert!(parse("").is_ok());
+        assert!(parse("[minify]\nkeep_comments = true").is_ok());
+        assert!(parse("[other]").is_err());
+        assert!(parse("minify = true").is_err());
+    }
+
+    #[test]
+    fn locate_errors() {
+        let error = |text| parse(text).err().unwrap();
+        assert_eq!(
+            error("[defaults]\nescape = \"no\""),
+            "origami.toml:2:10: `defaults.escape`: Expected a boolean"
+        );
+        assert_eq!(
+            error("[lints]\n\nunused = \"deny\""),
+            "origami.toml:3:1: Unknown setting `lints.unused`"
+        );
+        assert_eq!(
+            error("lints = 1"),
+            "origami.toml:1:9: `lints` must be a table"
+        );
+        assert_eq!(error("[other]"), "origami.toml:1:2: Unknown key `other`");
+        assert!(error("[defaults\n").starts_with("origami.toml:1:"));
+    }
+
+    #[test]
+    fn parse_defaults_and_lints() {
+        let config = parse(
+            "[defaults]\nescape = false\nself_closing = \"html\"\n[lints]\nunused_props = \"deny\"",
+        )
+        .unwrap();
+        assert!(!config.escape);
+        assert!(!config.self_closing_slash);
+        assert_eq!(config.unused_props, Level::Deny);
+        assert_eq!(config.similar_props, Level::Warn);
+        assert!(parse("[defaults]\nescape = \"no\"").is_err());
+        assert!(parse("[defaults]\nunused_props = \"allow\"").is_err());
+        assert!(parse("[lints]\nunused_props = \"error\"").is_err());
+    }
+
+    #[test]
+    fn apply_overrides() {
+        let mut config = Config::default();
+        config
+            .apply_overrides(quote! { minify = false, similar_props = "allow" })
+            .unwrap();
+        assert!(!config.minify);
+        assert_eq!(config.similar_props, Level::Allow);
+        assert!(config.apply_overrides(quote! { escape = 1 }).is_err());
+        assert!(config.apply_overrides(quote! { unknown = true }).is_err());
+    }
+
+    #[test]
+    fn bake_settings() {
+        let mut config =
+            parse("[defaults]\nescape = false\nminify_document = false\nself_closing = \"html\"")
+                .unwrap();
+        config.apply_overrides(quote! { minify = false }).unwrap();
+        config.bake();
+        let mut baked = Config::default();
+        baked.apply_overrides(config.overrides[0].clone()).unwrap();
+        assert!(!baked.escape);
+        assert!(!baked.minify);
+        assert!(!baked.minify_document);
+        assert!(!baked.self_closing_slash);
+    }
+
+    #[cfg(feature = "minify_html")]
+    #[test]
+    fn parse_minify_options() {
+        let config = parse("[minify]\nkeep_comments = true\nminify_js = false").unwrap();
+        let mut cfg = minify_html::Cfg {
+            minify_js: true,
+            ..Default::default()
+        };
+        config.apply_minify(&mut cfg);
+        assert!(cfg.keep_comments);
+        assert!(!cfg.minify_js);
+        assert!(parse("[minify]\nkeep_everything = true").is_err());
+        assert!(parse("[minify]\nkeep_comments = 1").is_err());
+    }
+
+    #[cfg(feature = "minify_html")]
+    #[test]
+    fn override_minify_options() {
+        let mut config = parse("[minify]\nkeep_comments = true").unwrap();
+        config.bake();
+        let mut baked = parse("[minify]\nminify_js = false").unwrap();
+        baked.apply_overrides(config.overrides[0].clone()).unwrap();
+        let mut cfg = minify_html::Cfg {
+            minify_js: true,
+            ..Default::default()
+        };
+        baked.apply_minify(&mut cfg);
+        assert!(cfg.keep_comments);
+        assert!(cfg.minify_js);
+        assert!(baked
+            .apply_overrides(quote! { minify(keep_everything) })
+            .is_err());
+    }
+}
@@ -3,10 +3,11 @@ use quote::ToTokens;
 use syn::parse_macro_input;
 
 use self::anon::Anon;
-use self::comp::Component;
+use self::comp::{Component, ComponentFile, Extends};
 
 mod anon;
 mod comp;
+mod config;
 mod utils;
 
 #[proc_macro]
@@ -16,7 +17,26 @@ pub fn comp(input: TokenStream) -> TokenStream {
         .into()
 }
 
+#[proc_macro]
+pub fn comp_file(input: TokenStream) -> TokenStream {
+    parse_macro_input!(input as ComponentFile)
+        .into_token_stream()
+        .into()
+}
+
+/// Renders markup into `s`, or the expression given with `string`, a `Buffer` or a `String`.
+///
+/// Rendered into a `String`, the CSP sources of `script` and `style` blocks are dropped, and
+/// `#provide`, `use_context`, `#outlet`, `#portal`, `once` and `nonce` don't compile.
 #[proc_macro]
 pub fn anon(input: TokenStream) -> TokenStream {
     parse_macro_input!(input as Anon).into_token_stream().into()
 }
+
+#[doc(hidden)]
+#[proc_macro]
+pub fn __extends(input: TokenStream) -> TokenStream {
+    parse_macro_input!(input as Extends)
+        .into_token_stream()
+        .into()
+}
@@ -1,16 +1,14 @@
+use proc_macro2::{Span, TokenStream};
+use quote::quote_spanned;
+
 pub mod kw {
     use syn::custom_keyword;
-    custom_keyword!(nominify);
     custom_keyword!(size_hint);
-    custom_keyword!(escape);
-    custom_keyword!(noescape);
     custom_keyword!(concat_args);
     custom_keyword!(concat_args_ident);
-    custom_keyword!(call);
     custom_keyword!(string);
     custom_keyword!(childrens);
-    custom_keyword!(script);
-    custom_keyword!(style);
+    custom_keyword!(config);
 }
 
 macro_rules! bail {
@@ -20,6 +18,21 @@ macro_rules! bail {
 }
 pub(crate) use bail;
 
+pub(crate) use origami_syntax::read_manifest_file;
+
+/// Emits `message` as a warning at `span`, through the use of a deprecated item, as procedural
+/// macros cannot emit warnings on stable.
+pub(crate) fn warning(span: Span, message: &str) -> TokenStream {
+    quote_spanned! {span=>
+        const _: () = {
+            #[deprecated(note = #message)]
+            #[allow(non_camel_case_types)]
+            struct origami_lint;
+            let _ = origami_lint;
+        };
+    }
+}
+
 macro_rules! combine_to_lit {
     ($($input:expr),*) => {{
         combine_to_lit!(@internal span => ::proc_macro2::Span::call_site(), $($input),*)
@@ -3,8 +3,8 @@ use syn::parse::{Parse, ParseStream};
 use syn::token::Brace;
 use syn::{Expr, Ident, LitStr, Token};
 
-use crate::utils::bail;
-use crate::utils::kw::{escape, noescape, nominify};
+use crate::bail;
+use crate::kw::{escape, noescape, nominify};
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub enum AttributeKey {
@@ -57,10 +57,10 @@ impl Parse for AttributeKey {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum AttributeValue {
     LitStr(LitStr),
-    Expr(Expr),
+    Expr(Box<Expr>),
 }
 
 impl Parse for AttributeValue {
@@ -72,13 +72,13 @@ impl Parse for AttributeValue {
             input.parse::<Token![@]>()?;
             let expr = input.parse()?;
             input.parse::<Token![;]>()?;
-            return Ok(Self::Expr(expr));
+            return Ok(Self::Expr(Box::new(expr)));
         }
         bail!(input, "Expected string or expression.")
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Attributes(pub IndexMap<AttributeKey, Option<AttributeValue>>);
 
 impl Parse for Attributes {
@@ -0,0 +1,714 @@
+use std::fmt::Debug;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::spanned::Spanned;
+use syn::token::{Comma, If, Paren};
+use syn::{braced, parenthesized, Expr, Ident, LitStr, Pat, Path, Token};
+
+use crate::attributes::{AttributeKey, AttributeValue, Attributes};
+use crate::css::scope_css;
+#[cfg(not(feature = "minify_html"))]
+use crate::kw::minify;
+use crate::kw::{
+    call, extends, include, nominify, once, outlet, portal, provide, scope, scoped, script, style,
+};
+#[cfg(feature = "html_escape")]
+use crate::kw::{escape, noescape};
+#[cfg(feature = "validate")]
+use crate::validate::{validate_script, validate_style};
+#[cfg(feature = "minify_html")]
+use crate::MinifyOptions;
+use crate::{bail, read_manifest_file};
+
+pub type Childrens = Vec<Children>;
+
+/// State of the parser, inherited by nested blocks.
+#[derive(Debug, Clone)]
+pub struct Context {
+    /// Whether text and expressions are escaped, changed by `escape` and `noescape`.
+    #[cfg(feature = "html_escape")]
+    pub escape: bool,
+    /// Attribute added to every element, set for components with `style scoped`.
+    pub scope: Option<String>,
+    /// Whether `#include` and `src_file` can read files, parsing fails on them otherwise.
+    pub read_files: bool,
+    /// Whether `script`, `style` and `#include` are minified when neither `minify(..)` nor
+    /// `nominify` is set.
+    #[cfg(feature = "minify_html")]
+    pub minify: bool,
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self {
+            #[cfg(feature = "html_escape")]
+            escape: true,
+            scope: None,
+            read_files: true,
+            #[cfg(feature = "minify_html")]
+            minify: true,
+        }
+    }
+}
+
+impl Context {
+    #[cfg(feature = "html_escape")]
+    fn parse_escape_no_escape(&mut self, input: ParseStream) -> syn::Result<()> {
+        if input.peek(escape) {
+            input.parse::<escape>()?;
+            self.escape = true;
+        } else if input.peek(noescape) {
+            input.parse::<noescape>()?;
+            self.escape = false;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum HtmlChildrens {
+    Childrens(Childrens),
+    SelfClosing,
+}
+
+#[derive(Debug, Clone)]
+pub struct CustomMatchArm {
+    pub body: Childrens,
+    pub pat: Pat,
+    pub guard: Option<(If, Expr)>,
+    pub comma: Option<Comma>,
+}
+
+/// A node of a template.
+#[derive(Debug, Clone)]
+pub enum Children {
+    /// `"text"`, `!` after it disabling escaping.
+    Text {
+        text: LitStr,
+        #[cfg(feature = "html_escape")]
+        escape: bool,
+    },
+    /// `@expr;`, `!` after it disabling escaping.
+    Expr {
+        expr: Expr,
+        #[cfg(feature = "html_escape")]
+        escape: bool,
+    },
+    /// `call path { props }` or `#extends path { blocks }`, whose tokens are passed to the
+    /// component macro.
+    CompCall {
+        comp: Path,
+        ts: TokenStream,
+        #[cfg(feature = "html_escape")]
+        escape: bool,
+    },
+    /// `if cond; { .. } else if cond; { .. } else { .. }`.
+    Cond {
+        if_: (Expr, Childrens),
+        else_ifs: Vec<(Expr, Childrens)>,
+        else_: Childrens,
+    },
+    /// `for pat in expr; { .. }`, `expr_b` being the pattern.
+    For {
+        expr_b: Expr,
+        expr_a: Expr,
+        childrens: Childrens,
+    },
+    /// An element, `tag attrs { .. }` or `tag attrs;`.
+    Html {
+        tag: Ident,
+        attrs: Attributes,
+        childrens: HtmlChildrens,
+    },
+    /// `match expr; { pat => { .. }, }`.
+    Match {
+        expr: Expr,
+        arms: Vec<CustomMatchArm>,
+    },
+    /// `#outlet name;`.
+    Outlet { name: Ident },
+    /// `#portal name { .. }`.
+    Portal { name: Ident, childrens: Childrens },
+    /// `#provide expr; { .. }`.
+    Provide { expr: Expr, childrens: Childrens },
+    /// `#scope "attr" { .. }` or `#scope none { .. }`, generated for `style scoped`.
+    Scope { childrens: Childrens },
+    /// `script attrs { "js" }`, or with `src_file="path"`.
+    Script {
+        text: Option<LitStr>,
+        attrs: Attributes,
+        /// Whether `nonce` without a value is set, rendering the nonce of the render context.
+        nonce: bool,
+        /// Options of the minifier, `None` with `nominify`.
+        #[cfg(feature = "minify_html")]
+        minify: Option<MinifyOptions>,
+        once: Option<Once>,
+        src_file: Option<LitStr>,
+    },
+    /// `style attrs { "css" }`, or with `src_file="path"`.
+    Style {
+        text: Option<LitStr>,
+        attrs: Attributes,
+        /// Whether `nonce` without a value is set, rendering the nonce of the render context.
+        nonce: bool,
+        /// Options of the minifier, `None` with `nominify`.
+        #[cfg(feature = "minify_html")]
+        minify: Option<MinifyOptions>,
+        once: Option<Once>,
+        src_file: Option<LitStr>,
+    },
+    /// `#include "path";`, with the content of the file read at parse time.
+    Include {
+        text: LitStr,
+        /// Options of the minifier, `None` with `nominify`.
+        #[cfg(feature = "minify_html")]
+        minify: Option<MinifyOptions>,
+        src_file: LitStr,
+    },
+}
+
+/// A `script` or `style` block rendered at most once per render.
+#[derive(Debug, Clone)]
+pub struct Once {
+    /// Outlet the block is hoisted into.
+    pub outlet: Option<Ident>,
+}
+
+impl Once {
+    fn parse(input: ParseStream) -> syn::Result<Option<Self>> {
+        if !input.peek(once) {
+            return Ok(None);
+        }
+        input.parse::<once>()?;
+        let outlet = if input.peek(Paren) {
+            let content;
+            parenthesized!(content in input);
+            Some(content.parse()?)
+        } else {
+            None
+        };
+        Ok(Some(Self { outlet }))
+    }
+}
+
+impl Children {
+    pub fn parse(input: ParseStream, pc: &mut Context) -> syn::Result<Self> {
+        if input.peek(LitStr) {
+            return parse_text(input, pc);
+        }
+        if input.peek(style) {
+            return parse_style(input, pc);
+        }
+        if input.peek(script) {
+            return parse_script(input, pc);
+        }
+        if input.peek(call) {
+            return parse_component(input, pc);
+        }
+        if input.peek(Token![@]) {
+            input.parse::<Token![@]>()?;
+            let expr: Expr = input.parse()?;
+            input.parse::<Token![;]>()?;
+            #[cfg(feature = "html_escape")]
+            let escape = if input.peek(Token![!]) {
+                input.parse::<Token![!]>()?;
+                false
+            } else {
+                pc.escape
+            };
+            return Ok(Children::Expr {
+                expr,
+                #[cfg(feature = "html_escape")]
+                escape,
+            });
+        }
+        if input.peek(Token![if]) {
+            return parse_conditional(input, pc);
+        }
+        if input.peek(Token![for]) {
+            return parse_for(input, pc);
+        }
+        if input.peek(Token![match]) {
+            return parse_match(input, pc);
+        }
+        if input.peek(Token![#]) {
+            return parse_directive(input, pc);
+        }
+        if input.peek(Ident) {
+            return parse_html(input, pc);
+        }
+        bail!(
+            input,
+            "Invalid input for `Children`. Expected one of the following: `LitStr`, `@`, `if`, `for`, `match`, `call`, `script`, `style`, `#provide`, `#extends`, `#outlet`, `#portal`, `#scope`, `#include` or `Ident`."
+        )
+    }
+}
+
+/// Parses the directives starting with `#`, which can't be mistaken for elements of the same
+/// name, like `#portal name { .. }` and `<portal>`.
+fn parse_directive(input: ParseStream, pc: &mut Context) -> syn::Result<Children> {
+    input.parse::<Token![#]>()?;
+    if input.peek(provide) {
+        return parse_provide(input, pc);
+    }
+    if input.peek(extends) {
+        return parse_extends(input, pc);
+    }
+    if input.peek(outlet) {
+        return parse_outlet(input);
+    }
+    if input.peek(portal) {
+        return parse_portal(input, pc);
+    }
+    if input.peek(scope) {
+        return parse_scope(input, pc);
+    }
+    if input.peek(include) {
+        return parse_include(input, pc);
+    }
+    bail!(
+        input,
+        "Expected one of the following after `#`: `provide`, `extends`, `outlet`, `portal`, `scope` or `include`."
+    )
+}
+
+/// Parses children until the end of `input`.
+pub fn parse_childrens(input: ParseStream, pc: &mut Context) -> syn::Result<Childrens> {
+    let mut childrens = Vec::new();
+    while !input.is_empty() {
+        childrens.push(Children::parse(input, pc)?);
+    }
+    Ok(childrens)
+}
+
+fn parse_block(input: ParseStream, pc: &mut Context) -> syn::Result<Childrens> {
+    #[cfg(feature = "html_escape")]
+    pc.parse_escape_no_escape(input)?;
+    let content;
+    braced!(content in input);
+    parse_childrens(&content, pc)
+}
+
+#[allow(unused_variables)]
+fn parse_text(input: ParseStream, pc: &mut Context) -> syn::Result<Children> {
+    Ok(Children::Text {
+        text: input.parse()?,
+        #[cfg(feature = "html_escape")]
+        escape: if input.peek(Token![!]) {
+            input.parse::<Token![!]>()?;
+            false
+        } else {
+            pc.escape
+        },
+    })
+}
+
+fn parse_component(
+    input: ParseStream,
+    #[allow(unused_variables)] pc: &mut Context,
+) -> syn::Result<Children> {
+    input.parse::<call>()?;
+    let comp = input.parse()?;
+    let content;
+    braced!(content in input);
+    let ts = content.call(TokenStream::parse)?;
+    #[cfg(feature = "html_escape")]
+    let escape = if input.peek(Token![!]) {
+        input.parse::<Token![!]>()?;
+        false
+    } else {
+        pc.escape
+    };
+    Ok(Children::CompCall {
+        comp,
+        ts,
+        #[cfg(feature = "html_escape")]
+        escape,
+    })
+}
+
+fn parse_extends(
+    input: ParseStream,
+    #[allow(unused_variables)] pc: &mut Context,
+) -> syn::Result<Children> {
+    input.parse::<extends>()?;
+    let comp: Path = input.parse()?;
+    let content;
+    braced!(content in input);
+    let blocks = content.call(TokenStream::parse)?;
+    #[cfg(feature = "html_escape")]
+    let escape = if input.peek(Token![!]) {
+        input.parse::<Token![!]>()?;
+        false
+    } else {
+        pc.escape
+    };
+    Ok(Children::CompCall {
+        ts: quote! {
+            extends [#comp] { #blocks }
+        },
+        comp,
+        #[cfg(feature = "html_escape")]
+        escape,
+    })
+}
+
+fn parse_conditional(input: ParseStream, pc: &mut Context) -> syn::Result<Children> {
+    let _span = input.parse::<Token![if]>()?.span();
+    let if_cond = input.parse::<Expr>()?;
+    input.parse::<Token![;]>()?;
+    let if_childrens = parse_block(input, pc)?;
+    let mut else_ifs = Vec::new();
+    while input.peek(Token![else]) && input.peek2(Token![if]) {
+        input.parse::<Token![else]>()?;
+        input.parse::<Token![if]>()?;
+        let cond = input.parse::<Expr>()?;
+        input.parse::<Token![;]>()?;
+        let childrens = parse_block(input, pc)?;
+        else_ifs.push((cond, childrens));
+    }
+    let else_ = if input.peek(Token![else]) {
+        input.parse::<Token![else]>()?;
+        parse_block(input, pc)?
+    } else {
+        Vec::new()
+    };
+    Ok(Children::Cond {
+        if_: (if_cond, if_childrens),
+        else_ifs,
+        else_,
+    })
+}
+
+fn parse_for(input: ParseStream, pc: &mut Context) -> syn::Result<Children> {
+    input.parse::<Token![for]>()?;
+    let expr_b: Expr = input.parse()?;
+    input.parse::<Token![in]>()?;
+    let expr_a: Expr = input.parse()?;
+    input.parse::<Token![;]>()?;
+    let childrens = parse_block(input, pc)?;
+    Ok(Children::For {
+        expr_b,
+        expr_a,
+        childrens,
+    })
+}
+
+fn parse_script(input: ParseStream, pc: &mut Context) -> syn::Result<Children> {
+    input.parse::<script>()?;
+    let once = Once::parse(input)?;
+    #[cfg(feature = "minify_html")]
+    let options = MinifyOptions::parse(input)?;
+    #[cfg(not(feature = "minify_html"))]
+    reject_minify_options(input)?;
+    let mut attrs: Attributes = input.parse()?;
+    #[cfg(feature = "html_escape")]
+    if attrs.0.contains_key(&AttributeKey::Escape) || attrs.0.contains_key(&AttributeKey::NoEscape)
+    {
+        bail!(
+            input,
+            "Cannot use `escape` or `noescape` with `script` or `style`"
+        );
+    }
+    let nonce = block_nonce(input, &mut attrs);
+    #[cfg(feature = "minify_html")]
+    let minify = block_minify(input, &attrs, options, pc)?;
+    let (text, src_file) = parse_text_or_src_file(input, pc, &mut attrs, "script")?;
+    #[cfg(feature = "validate")]
+    if let Some(text) = &text {
+        validate_script(text, src_file.as_ref().map(|(path, _)| path), &attrs)?;
+    }
+    let src_file = src_file.map(|(_, full_path)| full_path);
+    Ok(Children::Script {
+        attrs,
+        text,
+        nonce,
+        #[cfg(feature = "minify_html")]
+        minify,
+        once,
+        src_file,
+    })
+}
+
+fn parse_style(input: ParseStream, pc: &mut Context) -> syn::Result<Children> {
+    input.parse::<style>()?;
+    let scoped = if input.peek(scoped) {
+        let span = input.parse::<scoped>()?.span;
+        let Some(scope) = &pc.scope else {
+            bail!(span, "`style scoped` can only be used in components");
+        };
+        Some(scope.clone())
+    } else {
+        None
+    };
+    let once = Once::parse(input)?;
+    #[cfg(feature = "minify_html")]
+    let options = MinifyOptions::parse(input)?;
+    #[cfg(not(feature = "minify_html"))]
+    reject_minify_options(input)?;
+    let mut attrs: Attributes = input.parse()?;
+    #[cfg(feature = "html_escape")]
+    if attrs.0.contains_key(&AttributeKey::Escape) || attrs.0.contains_key(&AttributeKey::NoEscape)
+    {
+        bail!(
+            input,
+            "Cannot use `escape` or `noescape` with `script` or `style`"
+        );
+    }
+    let nonce = block_nonce(input, &mut attrs);
+    #[cfg(feature = "minify_html")]
+    let minify = block_minify(input, &attrs, options, pc)?;
+    let (mut text, src_file) = parse_text_or_src_file(input, pc, &mut attrs, "style")?;
+    #[cfg(feature = "validate")]
+    if let Some(css) = &text {
+        validate_style(css, src_file.as_ref().map(|(path, _)| path))?;
+    }
+    let src_file = src_file.map(|(_, full_path)| full_path);
+    if let (Some(scope), Some(css)) = (scoped, &text) {
+        text = Some(LitStr::new(&scope_css(&css.value(), &scope), css.span()));
+    }
+    Ok(Children::Style {
+        attrs,
+        text,
+        nonce,
+        #[cfg(feature = "minify_html")]
+        minify,
+        once,
+        src_file,
+    })
+}
+
+/// Removes `nonce` without a value from the attributes of a `script` or `style` block, a
+/// `nonce` with a value stays an attribute.
+fn block_nonce(input: ParseStream, attrs: &mut Attributes) -> bool {
+    let key = AttributeKey::Ident(Ident::new("nonce", input.span()));
+    let nonce = matches!(attrs.0.get(&key), Some(None));
+    if nonce {
+        attrs.0.shift_remove(&key);
+    }
+    nonce
+}
+
+/// Combines `minify(..)` and `nominify` of a `script` or `style` block.
+#[cfg(feature = "minify_html")]
+fn block_minify(
+    input: ParseStream,
+    attrs: &Attributes,
+    options: Option<MinifyOptions>,
+    pc: &Context,
+) -> syn::Result<Option<MinifyOptions>> {
+    if !attrs.0.contains_key(&AttributeKey::NoMinify) {
+        return Ok(options.or_else(|| pc.minify.then(MinifyOptions::default)));
+    }
+    if options.is_some() {
+        bail!(input, "Cannot use `minify(..)` with `nominify`");
+    }
+    Ok(None)
+}
+
+#[cfg(not(feature = "minify_html"))]
+fn reject_minify_options(input: ParseStream) -> syn::Result<()> {
+    if input.peek(minify) && input.peek2(Paren) {
+        bail!(
+            input,
+            "It is not possible to use `minify(..)` without `minify_html` feature."
+        );
+    }
+    Ok(())
+}
+
+fn parse_html(input: ParseStream, pc: &mut Context) -> syn::Result<Children> {
+    let tag: Ident = input.parse()?;
+    let mut attrs: Attributes = input.parse()?;
+    if let Some(scope) = &pc.scope {
+        attrs
+            .0
+            .insert(AttributeKey::LitStr(LitStr::new(scope, tag.span())), None);
+    }
+    #[cfg(feature = "html_escape")]
+    if attrs.0.contains_key(&AttributeKey::Escape) {
+        pc.escape = true;
+    }
+    #[cfg(feature = "html_escape")]
+    if attrs.0.contains_key(&AttributeKey::NoEscape) {
+        pc.escape = false;
+    }
+    #[cfg(feature = "minify_html")]
+    if attrs.0.contains_key(&AttributeKey::NoMinify) {
+        bail!(
+            input,
+            "`nominify` can only be used with `script` or `style` tags"
+        );
+    }
+    if input.peek(Token![;]) {
+        input.parse::<Token![;]>()?;
+        return Ok(Children::Html {
+            tag,
+            attrs,
+            childrens: HtmlChildrens::SelfClosing,
+        });
+    }
+    Ok(Children::Html {
+        tag,
+        attrs,
+        childrens: HtmlChildrens::Childrens(parse_block(input, pc)?),
+    })
+}
+
+fn parse_match(input: ParseStream, pc: &mut Context) -> syn::Result<Children> {
+    input.parse::<Token![match]>()?;
+    let expr: Expr = input.parse()?;
+    input.parse::<Token![;]>()?;
+    #[cfg(feature = "html_escape")]
+    pc.parse_escape_no_escape(input)?;
+    let mut arms = Vec::new();
+    let content;
+    braced!(content in input);
+    while !content.is_empty() {
+        let pat = Pat::parse_multi(&content)?;
+        let guard = if content.peek(Token![if]) {
+            Some((content.parse::<Token![if]>()?, content.parse()?))
+        } else {
+            None
+        };
+        content.parse::<Token![=>]>()?;
+        let body = parse_block(&content, pc)?;
+        let comma = if content.peek(Token![,]) {
+            Some(content.parse::<Token![,]>()?)
+        } else {
+            None
+        };
+        arms.push(CustomMatchArm {
+            pat,
+            guard,
+            body,
+            comma,
+        })
+    }
+    Ok(Children::Match { expr, arms })
+}
+
+fn parse_provide(input: ParseStream, pc: &mut Context) -> syn::Result<Children> {
+    input.parse::<provide>()?;
+    let expr: Expr = input.parse()?;
+    input.parse::<Token![;]>()?;
+    let childrens = parse_block(input, pc)?;
+    Ok(Children::Provide { expr, childrens })
+}
+
+fn parse_outlet(input: ParseStream) -> syn::Result<Children> {
+    input.parse::<outlet>()?;
+    let name = input.parse()?;
+    input.parse::<Token![;]>()?;
+    Ok(Children::Outlet { name })
+}
+
+fn parse_portal(input: ParseStream, pc: &mut Context) -> syn::Result<Children> {
+    input.parse::<portal>()?;
+    let name = input.parse()?;
+    let childrens = parse_block(input, pc)?;
+    Ok(Children::Portal { name, childrens })
+}
+
+fn parse_scope(input: ParseStream, pc: &mut Context) -> syn::Result<Children> {
+    input.parse::<scope>()?;
+    let scope = if input.peek(LitStr) {
+        Some(input.parse::<LitStr>()?.value())
+    } else {
+        let none: Ident = input.parse()?;
+        if none != "none" {
+            bail!(none, "Expected a string literal or `none`");
+        }
+        None
+    };
+    let prev = std::mem::replace(&mut pc.scope, scope);
+    let childrens = parse_block(input, pc);
+    pc.scope = prev;
+    Ok(Children::Scope {
+        childrens: childrens?,
+    })
+}
+
+/// Content of a block, with the path of its `src_file` as written and the full path.
+type BlockContent = (Option<LitStr>, Option<(LitStr, LitStr)>);
+
+/// Parses the inline content of a `script` or `style` block, or reads it from the file given with
+/// `src_file`, in which case the block can be replaced by `;`.
+fn parse_text_or_src_file(
+    input: ParseStream,
+    pc: &Context,
+    attrs: &mut Attributes,
+    tag: &str,
+) -> syn::Result<BlockContent> {
+    let src_file = match attrs
+        .0
+        .shift_remove(&AttributeKey::Ident(Ident::new("src_file", input.span())))
+    {
+        Some(Some(AttributeValue::LitStr(path))) => Some(path),
+        Some(_) => bail!(input, "`src_file` expects a string literal"),
+        None => None,
+    };
+    if src_file.is_some() && input.peek(Token![;]) {
+        input.parse::<Token![;]>()?;
+    } else {
+        let content;
+        braced!(content in input);
+        let text: Option<LitStr> = content.parse()?;
+        if !content.is_empty() {
+            bail!(content, format!("Expected end of `{}` block", tag));
+        }
+        if src_file.is_none() {
+            return Ok((text, None));
+        }
+        if let Some(text) = text {
+            bail!(text, "Cannot use `src_file` with inline content");
+        }
+    }
+    let Some(path) = src_file else { unreachable!() };
+    let (text, full_path) = read_src_file(&path, pc)?;
+    Ok((Some(text), Some((path, full_path))))
+}
+
+fn parse_include(input: ParseStream, pc: &Context) -> syn::Result<Children> {
+    input.parse::<include>()?;
+    #[cfg(feature = "minify_html")]
+    let minify = if input.peek(nominify) {
+        input.parse::<nominify>()?;
+        None
+    } else {
+        MinifyOptions::parse(input)?.or_else(|| pc.minify.then(MinifyOptions::default))
+    };
+    #[cfg(not(feature = "minify_html"))]
+    if input.peek(nominify) {
+        bail!(
+            input,
+            "It is not possible to use `\"nominify\"` without `minify_html` feature."
+        );
+    }
+    #[cfg(not(feature = "minify_html"))]
+    reject_minify_options(input)?;
+    let path: LitStr = input.parse()?;
+    input.parse::<Token![;]>()?;
+    let (text, src_file) = read_src_file(&path, pc)?;
+    Ok(Children::Include {
+        text,
+        #[cfg(feature = "minify_html")]
+        minify,
+        src_file,
+    })
+}
+
+/// Reads the file at `path` relative to `CARGO_MANIFEST_DIR`, returning its content and
+/// absolute path as literals.
+fn read_src_file(path: &LitStr, pc: &Context) -> syn::Result<(LitStr, LitStr)> {
+    if !pc.read_files {
+        bail!(path, "Files cannot be read by this template");
+    }
+    let (text, full_path) = read_manifest_file(path)?;
+    Ok((
+        LitStr::new(&text, path.span()),
+        LitStr::new(&full_path, path.span()),
+    ))
+}
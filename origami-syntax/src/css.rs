@@ -0,0 +1,146 @@
+//! Rewriting of `style scoped` selectors, so they only match elements with the scope attribute.
+
+/// At-rules whose blocks contain style rules.
+const GROUPING_AT_RULES: [&str; 5] = ["media", "supports", "container", "layer", "document"];
+
+/// Adds `[attr]` to the last compound selector of every style rule in `css`.
+pub fn scope_css(css: &str, attr: &str) -> String {
+    let mut out = String::with_capacity(css.len());
+    scope_rules(css, attr, &mut out);
+    out
+}
+
+fn scope_rules(css: &str, attr: &str, out: &mut String) {
+    let mut rest = css;
+    while let Some((end, delim)) = find_top_level(rest, &['{', ';']) {
+        let prelude = &rest[..end];
+        if delim == ';' {
+            out.push_str(&rest[..=end]);
+            rest = &rest[end + 1..];
+            continue;
+        }
+        let body_end = find_block_end(&rest[end + 1..]).map_or(rest.len(), |i| end + 1 + i);
+        let body = &rest[end + 1..body_end];
+        match prelude.trim_start().strip_prefix('@') {
+            Some(at_rule)
+                if GROUPING_AT_RULES.iter().any(|name| {
+                    at_rule.starts_with(name)
+                        && !at_rule[name.len()..]
+                            .starts_with(|c: char| c.is_alphanumeric() || c == '-')
+                }) =>
+            {
+                out.push_str(prelude);
+                out.push('{');
+                scope_rules(body, attr, out);
+            }
+            Some(_) => {
+                out.push_str(prelude);
+                out.push('{');
+                out.push_str(body);
+            }
+            None => {
+                scope_selectors(prelude, attr, out);
+                out.push('{');
+                out.push_str(body);
+            }
+        }
+        if body_end < rest.len() {
+            out.push('}');
+            rest = &rest[body_end + 1..];
+        } else {
+            rest = "";
+        }
+    }
+    out.push_str(rest);
+}
+
+fn scope_selectors(prelude: &str, attr: &str, out: &mut String) {
+    let mut rest = prelude;
+    loop {
+        let (selector, next) = match find_top_level(rest, &[',']) {
+            Some((i, _)) => (&rest[..i], Some(&rest[i + 1..])),
+            None => (rest, None),
+        };
+        let trimmed = selector.trim_end();
+        let compound_start = last_compound_start(trimmed);
+        let insert_at = find_top_level(&trimmed[compound_start..], &[':'])
+            .map_or(trimmed.len(), |(i, _)| compound_start + i);
+        out.push_str(&trimmed[..insert_at]);
+        out.push('[');
+        out.push_str(attr);
+        out.push(']');
+        out.push_str(&trimmed[insert_at..]);
+        out.push_str(&selector[trimmed.len()..]);
+        match next {
+            Some(next) => {
+                out.push(',');
+                rest = next;
+            }
+            None => break,
+        }
+    }
+}
+
+/// Returns the start of the last compound selector, after the last combinator.
+fn last_compound_start(selector: &str) -> usize {
+    let mut start = 0;
+    let mut depth = 0;
+    let mut quote = None;
+    for (i, c) in selector.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(' | '[') => depth += 1,
+            (None, ')' | ']') => depth -= 1,
+            (None, c) if depth == 0 && (c.is_whitespace() || matches!(c, '>' | '+' | '~')) => {
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    start
+}
+
+/// Finds the first of `delims` outside of strings, comments, parentheses and brackets.
+fn find_top_level(s: &str, delims: &[char]) -> Option<(usize, char)> {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut chars = s.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), '\\') => {
+                chars.next();
+            }
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '/') if matches!(chars.peek(), Some((_, '*'))) => {
+                let end = s[i + 2..].find("*/").map_or(s.len(), |j| i + 2 + j + 2);
+                while chars.peek().is_some_and(|(j, _)| *j < end) {
+                    chars.next();
+                }
+            }
+            (None, c) if depth == 0 && delims.contains(&c) => return Some((i, c)),
+            (None, '(' | '[') => depth += 1,
+            (None, ')' | ']') => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Finds the `}` closing a block whose `{` was just consumed.
+fn find_block_end(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut offset = 0;
+    while let Some((i, c)) = find_top_level(&s[offset..], &['{', '}']) {
+        match c {
+            '{' => depth += 1,
+            _ if depth == 0 => return Some(offset + i),
+            _ => depth -= 1,
+        }
+        offset += i + 1;
+    }
+    None
+}
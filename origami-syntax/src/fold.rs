@@ -0,0 +1,206 @@
+//! Rewriting of the syntax tree by value.
+//!
+//! Each method of [`Fold`] defaults to the function of the same name, which folds the nodes
+//! inside. Overriding methods should call it to keep rewriting the tree.
+
+use syn::{Expr, Ident, LitStr, Pat};
+
+use crate::{
+    AttributeKey, AttributeValue, Attributes, Children, Childrens, CustomMatchArm, HtmlChildrens,
+};
+
+pub trait Fold {
+    fn fold_childrens(&mut self, childrens: Childrens) -> Childrens {
+        fold_childrens(self, childrens)
+    }
+
+    fn fold_children(&mut self, children: Children) -> Children {
+        fold_children(self, children)
+    }
+
+    fn fold_attributes(&mut self, attrs: Attributes) -> Attributes {
+        fold_attributes(self, attrs)
+    }
+
+    fn fold_match_arm(&mut self, arm: CustomMatchArm) -> CustomMatchArm {
+        fold_match_arm(self, arm)
+    }
+
+    fn fold_text(&mut self, text: LitStr) -> LitStr {
+        text
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        expr
+    }
+
+    fn fold_pat(&mut self, pat: Pat) -> Pat {
+        pat
+    }
+
+    fn fold_ident(&mut self, ident: Ident) -> Ident {
+        ident
+    }
+}
+
+pub fn fold_childrens<F: Fold + ?Sized>(f: &mut F, childrens: Childrens) -> Childrens {
+    childrens
+        .into_iter()
+        .map(|children| f.fold_children(children))
+        .collect()
+}
+
+pub fn fold_children<F: Fold + ?Sized>(f: &mut F, children: Children) -> Children {
+    match children {
+        Children::Text {
+            text,
+            #[cfg(feature = "html_escape")]
+            escape,
+        } => Children::Text {
+            text: f.fold_text(text),
+            #[cfg(feature = "html_escape")]
+            escape,
+        },
+        Children::Expr {
+            expr,
+            #[cfg(feature = "html_escape")]
+            escape,
+        } => Children::Expr {
+            expr: f.fold_expr(expr),
+            #[cfg(feature = "html_escape")]
+            escape,
+        },
+        children @ Children::CompCall { .. } => children,
+        Children::Cond {
+            if_: (cond, childrens),
+            else_ifs,
+            else_,
+        } => Children::Cond {
+            if_: (f.fold_expr(cond), f.fold_childrens(childrens)),
+            else_ifs: else_ifs
+                .into_iter()
+                .map(|(cond, childrens)| (f.fold_expr(cond), f.fold_childrens(childrens)))
+                .collect(),
+            else_: f.fold_childrens(else_),
+        },
+        Children::For {
+            expr_b,
+            expr_a,
+            childrens,
+        } => Children::For {
+            expr_b: f.fold_expr(expr_b),
+            expr_a: f.fold_expr(expr_a),
+            childrens: f.fold_childrens(childrens),
+        },
+        Children::Html {
+            tag,
+            attrs,
+            childrens,
+        } => Children::Html {
+            tag: f.fold_ident(tag),
+            attrs: f.fold_attributes(attrs),
+            childrens: match childrens {
+                HtmlChildrens::Childrens(childrens) => {
+                    HtmlChildrens::Childrens(f.fold_childrens(childrens))
+                }
+                HtmlChildrens::SelfClosing => HtmlChildrens::SelfClosing,
+            },
+        },
+        Children::Match { expr, arms } => Children::Match {
+            expr: f.fold_expr(expr),
+            arms: arms.into_iter().map(|arm| f.fold_match_arm(arm)).collect(),
+        },
+        Children::Outlet { name } => Children::Outlet {
+            name: f.fold_ident(name),
+        },
+        Children::Portal { name, childrens } => Children::Portal {
+            name: f.fold_ident(name),
+            childrens: f.fold_childrens(childrens),
+        },
+        Children::Provide { expr, childrens } => Children::Provide {
+            expr: f.fold_expr(expr),
+            childrens: f.fold_childrens(childrens),
+        },
+        Children::Scope { childrens } => Children::Scope {
+            childrens: f.fold_childrens(childrens),
+        },
+        Children::Script {
+            text,
+            attrs,
+            nonce,
+            #[cfg(feature = "minify_html")]
+            minify,
+            once,
+            src_file,
+        } => Children::Script {
+            attrs: f.fold_attributes(attrs),
+            text: text.map(|text| f.fold_text(text)),
+            nonce,
+            #[cfg(feature = "minify_html")]
+            minify,
+            once,
+            src_file,
+        },
+        Children::Style {
+            text,
+            attrs,
+            nonce,
+            #[cfg(feature = "minify_html")]
+            minify,
+            once,
+            src_file,
+        } => Children::Style {
+            attrs: f.fold_attributes(attrs),
+            text: text.map(|text| f.fold_text(text)),
+            nonce,
+            #[cfg(feature = "minify_html")]
+            minify,
+            once,
+            src_file,
+        },
+        Children::Include {
+            text,
+            #[cfg(feature = "minify_html")]
+            minify,
+            src_file,
+        } => Children::Include {
+            text: f.fold_text(text),
+            #[cfg(feature = "minify_html")]
+            minify,
+            src_file,
+        },
+    }
+}
+
+pub fn fold_attributes<F: Fold + ?Sized>(f: &mut F, attrs: Attributes) -> Attributes {
+    Attributes(
+        attrs
+            .0
+            .into_iter()
+            .map(|(key, value)| {
+                let key = match key {
+                    AttributeKey::Ident(ident) => AttributeKey::Ident(f.fold_ident(ident)),
+                    AttributeKey::LitStr(text) => AttributeKey::LitStr(f.fold_text(text)),
+                    #[allow(unreachable_patterns)]
+                    key => key,
+                };
+                let value = value.map(|value| match value {
+                    AttributeValue::LitStr(text) => AttributeValue::LitStr(f.fold_text(text)),
+                    AttributeValue::Expr(expr) => {
+                        AttributeValue::Expr(Box::new(f.fold_expr(*expr)))
+                    }
+                });
+                (key, value)
+            })
+            .collect(),
+    )
+}
+
+pub fn fold_match_arm<F: Fold + ?Sized>(f: &mut F, arm: CustomMatchArm) -> CustomMatchArm {
+    CustomMatchArm {
+        pat: f.fold_pat(arm.pat),
+        guard: arm.guard.map(|(if_, guard)| (if_, f.fold_expr(guard))),
+        body: f.fold_childrens(arm.body),
+        comma: arm.comma,
+    }
+}
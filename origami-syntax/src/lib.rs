@@ -0,0 +1,105 @@
+//! # Origami Syntax
+//! Parser and syntax tree of the templates of [origami-engine](https://docs.rs/origami-engine),
+//! for tools working with templates, e.g. formatters, linters or converters.
+//!
+//! Templates are parsed into [`Children`] with [`parse_childrens`]. Nodes keep the `syn` types
+//! they are parsed into, so every literal, identifier, expression and pattern has its span. The
+//! tree is walked with [`Visit`](visit::Visit) and rewritten with [`Fold`](fold::Fold).
+//!
+//! ```rust
+//! use origami_syntax::visit::{self, Visit};
+//! use origami_syntax::{parse_childrens, Children, Context};
+//! use syn::parse::Parser;
+//!
+//! // Collects the tags of all elements.
+//! struct Tags(Vec<String>);
+//!
+//! impl Visit for Tags {
+//!     fn visit_children(&mut self, children: &Children) {
+//!         if let Children::Html { tag, .. } = children {
+//!             self.0.push(tag.to_string());
+//!         }
+//!         visit::visit_children(self, children);
+//!     }
+//! }
+//!
+//! let ts = quote::quote! {
+//!     div {
+//!         h1 { "Title" }
+//!         for item in items; { p { @item; } }
+//!     }
+//! };
+//! let childrens = (|input: syn::parse::ParseStream| parse_childrens(input, &mut Context::default()))
+//!     .parse2(ts)
+//!     .unwrap();
+//!
+//! let mut tags = Tags(Vec::new());
+//! tags.visit_childrens(&childrens);
+//! assert_eq!(tags.0, ["div", "h1", "p"]);
+//! ```
+//!
+//! The `html_escape` and `minify_html` features enable the `escape`, `noescape`, `nominify` and
+//! `minify(..)` keywords, as the features of the same name of origami-engine. The `validate`
+//! feature fails the parsing of `script` and `style` blocks with invalid JavaScript or CSS.
+
+mod attributes;
+mod children;
+mod css;
+pub mod fold;
+#[cfg(feature = "minify_html")]
+mod minify;
+#[cfg(feature = "validate")]
+mod validate;
+pub mod visit;
+
+pub use self::attributes::{AttributeKey, AttributeValue, Attributes};
+pub use self::children::{
+    parse_childrens, Children, Childrens, Context, CustomMatchArm, HtmlChildrens, Once,
+};
+pub use self::css::scope_css;
+#[cfg(feature = "minify_html")]
+pub use self::minify::{set_minify_option, MinifyOptions};
+#[doc(no_inline)]
+pub use indexmap::IndexMap;
+
+/// Keywords of the template syntax.
+pub mod kw {
+    use syn::custom_keyword;
+    custom_keyword!(minify);
+    custom_keyword!(nominify);
+    custom_keyword!(escape);
+    custom_keyword!(noescape);
+    custom_keyword!(call);
+    custom_keyword!(script);
+    custom_keyword!(style);
+    custom_keyword!(provide);
+    custom_keyword!(extends);
+    custom_keyword!(outlet);
+    custom_keyword!(portal);
+    custom_keyword!(once);
+    custom_keyword!(scope);
+    custom_keyword!(scoped);
+    custom_keyword!(include);
+}
+
+macro_rules! bail {
+    ($input:expr, $msg:expr) => {
+        return Err(::syn::Error::new($input.span(), $msg))
+    };
+}
+pub(crate) use bail;
+
+/// Reads the file at `path` relative to `CARGO_MANIFEST_DIR`, returning its content and
+/// absolute path.
+pub fn read_manifest_file(path: &syn::LitStr) -> syn::Result<(String, String)> {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR")
+        .map_err(|_| syn::Error::new(path.span(), "`CARGO_MANIFEST_DIR` is not set"))?;
+    let full_path = std::path::Path::new(&manifest_dir).join(path.value());
+    let text = std::fs::read_to_string(&full_path).map_err(|e| {
+        syn::Error::new(
+            path.span(),
+            format!("Cannot read `{}`: {}", full_path.display(), e),
+        )
+    })?;
+    Ok((text, full_path.to_string_lossy().into_owned()))
+}
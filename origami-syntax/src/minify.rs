@@ -0,0 +1,88 @@
+use minify_html::Cfg;
+use syn::parse::ParseStream;
+use syn::punctuated::Punctuated;
+use syn::token::Paren;
+use syn::{parenthesized, Ident, LitBool, Token};
+
+use crate::bail;
+use crate::kw::minify;
+
+/// Options of the minifier set by `minify(..)`, e.g. `script minify(minify_js = false) { .. }`.
+///
+/// Options are the fields of [`minify_html::Cfg`], an option without a value is set to `true`.
+#[derive(Debug, Clone, Default)]
+pub struct MinifyOptions(pub Vec<(Ident, bool)>);
+
+impl MinifyOptions {
+    pub fn parse(input: ParseStream) -> syn::Result<Option<Self>> {
+        if !(input.peek(minify) && input.peek2(Paren)) {
+            return Ok(None);
+        }
+        input.parse::<minify>()?;
+        let content;
+        parenthesized!(content in input);
+        let mut options = Vec::new();
+        for option in Punctuated::<MinifyOption, Token![,]>::parse_terminated(&content)? {
+            if !set_minify_option(&mut Cfg::default(), &option.name.to_string(), option.value) {
+                bail!(
+                    option.name,
+                    format!("Unknown minify option `{}`", option.name)
+                );
+            }
+            options.push((option.name, option.value));
+        }
+        Ok(Some(Self(options)))
+    }
+
+    /// Sets the options on `cfg`.
+    pub fn apply(&self, cfg: &mut Cfg) {
+        for (name, value) in &self.0 {
+            set_minify_option(cfg, &name.to_string(), *value);
+        }
+    }
+}
+
+struct MinifyOption {
+    name: Ident,
+    value: bool,
+}
+
+impl syn::parse::Parse for MinifyOption {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        let value = if input.peek(Token![=]) {
+            input.parse::<Token![=]>()?;
+            input.parse::<LitBool>()?.value
+        } else {
+            true
+        };
+        Ok(Self { name, value })
+    }
+}
+
+/// Sets the field `name` of `cfg`, returning `false` if there is no such field.
+pub fn set_minify_option(cfg: &mut Cfg, name: &str, value: bool) -> bool {
+    let field = match name {
+        "do_not_minify_doctype" => &mut cfg.do_not_minify_doctype,
+        "ensure_spec_compliant_unquoted_attribute_values" => {
+            &mut cfg.ensure_spec_compliant_unquoted_attribute_values
+        }
+        "keep_closing_tags" => &mut cfg.keep_closing_tags,
+        "keep_comments" => &mut cfg.keep_comments,
+        "keep_html_and_head_opening_tags" => &mut cfg.keep_html_and_head_opening_tags,
+        "keep_input_type_text_attr" => &mut cfg.keep_input_type_text_attr,
+        "keep_spaces_between_attributes" => &mut cfg.keep_spaces_between_attributes,
+        "keep_ssi_comments" => &mut cfg.keep_ssi_comments,
+        "minify_css" => &mut cfg.minify_css,
+        "minify_js" => &mut cfg.minify_js,
+        "preserve_brace_template_syntax" => &mut cfg.preserve_brace_template_syntax,
+        "preserve_chevron_percent_template_syntax" => {
+            &mut cfg.preserve_chevron_percent_template_syntax
+        }
+        "remove_bangs" => &mut cfg.remove_bangs,
+        "remove_processing_instructions" => &mut cfg.remove_processing_instructions,
+        _ => return false,
+    };
+    *field = value;
+    true
+}
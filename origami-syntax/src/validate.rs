@@ -0,0 +1,189 @@
+//! Validation of the CSS and JavaScript of `style` and `script` blocks, which the minifier would
+//! otherwise leave mangled without an error, and browsers would ignore.
+
+use lightningcss::stylesheet::{ParserOptions, StyleSheet};
+use minify_html_common::spec::script::JAVASCRIPT_MIME_TYPES;
+use minify_js::{Session, TopLevelMode};
+use proc_macro2::Span;
+use syn::{Ident, LitStr};
+
+use crate::attributes::{AttributeKey, AttributeValue, Attributes};
+
+/// Fails if the content of a `style` block is not valid CSS. `src_file` is the path of the file
+/// the content was read from, if any.
+pub(crate) fn validate_style(text: &LitStr, src_file: Option<&LitStr>) -> syn::Result<()> {
+    let css = text.value();
+    let Err(err) = StyleSheet::parse(&css, ParserOptions::default()) else {
+        return Ok(());
+    };
+    let offset = match &err.loc {
+        Some(loc) => offset_of(&css, loc.line as usize + 1, loc.column as usize),
+        None => 0,
+    };
+    Err(error(text, src_file, &css, offset, "CSS", err.kind))
+}
+
+/// Fails if the content of a `script` block is not valid JavaScript, blocks of other types
+/// are not checked.
+pub(crate) fn validate_script(
+    text: &LitStr,
+    src_file: Option<&LitStr>,
+    attrs: &Attributes,
+) -> syn::Result<()> {
+    let mode = match attrs
+        .0
+        .get(&AttributeKey::Ident(Ident::new("type", text.span())))
+    {
+        None | Some(None) => TopLevelMode::Global,
+        Some(Some(AttributeValue::LitStr(ty))) => match ty.value().trim() {
+            "" => TopLevelMode::Global,
+            "module" => TopLevelMode::Module,
+            ty if JAVASCRIPT_MIME_TYPES.contains(ty.to_ascii_lowercase().as_bytes()) => {
+                TopLevelMode::Global
+            }
+            _ => return Ok(()),
+        },
+        Some(Some(AttributeValue::Expr(_))) => return Ok(()),
+    };
+    let js = text.value();
+    let session = Session::new();
+    let Err(err) = minify_js::minify(&session, mode, js.as_bytes(), &mut Vec::new()) else {
+        return Ok(());
+    };
+    let message = match err.actual_token {
+        Some(token) => format!("{:?}, found {:?}", err.typ, token),
+        None => format!("{:?}", err.typ),
+    };
+    Err(error(
+        text,
+        src_file,
+        &js,
+        err.source.start(),
+        "JavaScript",
+        message,
+    ))
+}
+
+/// Error at `offset` of the content, as `path:line:column` of the file it was read from, or of
+/// the source file of the literal. Where the literal cannot be located, the line and column are
+/// those of the content.
+fn error(
+    text: &LitStr,
+    src_file: Option<&LitStr>,
+    content: &str,
+    offset: usize,
+    language: &str,
+    message: impl std::fmt::Display,
+) -> syn::Error {
+    let offset = offset.min(content.len());
+    let (line, column) = line_column(&content[..offset], 1, 1);
+    if let Some(path) = src_file {
+        return syn::Error::new(
+            path.span(),
+            format!(
+                "{}:{}:{}: Invalid {}: {}",
+                path.value(),
+                line,
+                column,
+                language,
+                message
+            ),
+        );
+    }
+    let token = text.token().to_string();
+    let Some(index) = token_offset(&token, offset) else {
+        return syn::Error::new(
+            text.span(),
+            format!(
+                "Invalid {} at line {}, column {} of the block: {}",
+                language, line, column, message
+            ),
+        );
+    };
+    let span = text.span();
+    let start = span.start();
+    // Positions are unknown to compilers older than 1.88.
+    let location = if start.line == 0 {
+        format!("line {}, column {} of the block", line, column)
+    } else {
+        let (line, column) = line_column(&token[..index], start.line, start.column + 1);
+        format!("{}:{}:{}", span.file(), line, column)
+    };
+    syn::Error::new(
+        span_at(text, &token, index),
+        format!("Invalid {} at {}: {}", language, location, message),
+    )
+}
+
+/// 1-based line and column after `text`, starting at `line` and `column`.
+fn line_column(text: &str, line: usize, column: usize) -> (usize, usize) {
+    match text.rfind('\n') {
+        Some(i) => (
+            line + text.matches('\n').count(),
+            text[i + 1..].chars().count() + 1,
+        ),
+        None => (line, column + text.chars().count()),
+    }
+}
+
+/// Byte offset of a 1-based line and column.
+fn offset_of(content: &str, line: usize, column: usize) -> usize {
+    let start: usize = content
+        .split_inclusive('\n')
+        .take(line - 1)
+        .map(str::len)
+        .sum();
+    start
+        + content[start..]
+            .char_indices()
+            .nth(column.saturating_sub(1))
+            .map_or(content.len() - start, |(i, _)| i)
+}
+
+/// Offset in the source of a string literal `token` of the byte at `offset` of its value.
+fn token_offset(token: &str, offset: usize) -> Option<usize> {
+    if let Some(raw) = token.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        return Some(1 + hashes + 1 + offset);
+    }
+    let mut chars = token.strip_prefix('"')?.char_indices().peekable();
+    let mut value = 0;
+    while let Some((i, c)) = chars.next() {
+        if value >= offset {
+            return (value == offset).then_some(i + 1);
+        }
+        if c != '\\' {
+            value += c.len_utf8();
+            continue;
+        }
+        match chars.next()?.1 {
+            'x' => {
+                chars.nth(1)?;
+                value += 1;
+            }
+            'u' => {
+                let code: String = chars
+                    .by_ref()
+                    .map(|(_, c)| c)
+                    .take_while(|c| *c != '}')
+                    .filter(|c| *c != '{')
+                    .collect();
+                value += char::from_u32(u32::from_str_radix(&code, 16).ok()?)?.len_utf8();
+            }
+            // Line continuations skip the line break and the leading whitespace.
+            '\n' | '\r' => while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {},
+            _ => value += 1,
+        }
+    }
+    None
+}
+
+/// Span of the character at `index` of the literal, or of the whole literal where the compiler
+/// cannot point into it.
+fn span_at(text: &LitStr, token: &str, index: usize) -> Span {
+    let end = token[index..]
+        .chars()
+        .next()
+        .map_or(index, |c| index + c.len_utf8());
+    text.token().subspan(index..end).unwrap_or(text.span())
+}
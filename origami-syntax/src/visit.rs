@@ -0,0 +1,126 @@
+//! Traversal of the syntax tree by reference.
+//!
+//! Each method of [`Visit`] defaults to the function of the same name, which visits the nodes
+//! inside. Overriding methods should call it to keep walking the tree.
+
+use syn::{Expr, Ident, LitStr, Pat};
+
+use crate::{AttributeKey, AttributeValue, Attributes, Children, CustomMatchArm, HtmlChildrens};
+
+pub trait Visit {
+    fn visit_childrens(&mut self, childrens: &[Children]) {
+        visit_childrens(self, childrens);
+    }
+
+    fn visit_children(&mut self, children: &Children) {
+        visit_children(self, children);
+    }
+
+    fn visit_attributes(&mut self, attrs: &Attributes) {
+        visit_attributes(self, attrs);
+    }
+
+    fn visit_match_arm(&mut self, arm: &CustomMatchArm) {
+        visit_match_arm(self, arm);
+    }
+
+    fn visit_text(&mut self, _text: &LitStr) {}
+
+    fn visit_expr(&mut self, _expr: &Expr) {}
+
+    fn visit_pat(&mut self, _pat: &Pat) {}
+
+    fn visit_ident(&mut self, _ident: &Ident) {}
+}
+
+pub fn visit_childrens<V: Visit + ?Sized>(v: &mut V, childrens: &[Children]) {
+    for children in childrens {
+        v.visit_children(children);
+    }
+}
+
+pub fn visit_children<V: Visit + ?Sized>(v: &mut V, children: &Children) {
+    match children {
+        Children::Text { text, .. } => v.visit_text(text),
+        Children::Expr { expr, .. } => v.visit_expr(expr),
+        Children::CompCall { .. } => {}
+        Children::Cond {
+            if_,
+            else_ifs,
+            else_,
+        } => {
+            for (cond, childrens) in std::iter::once(if_).chain(else_ifs) {
+                v.visit_expr(cond);
+                v.visit_childrens(childrens);
+            }
+            v.visit_childrens(else_);
+        }
+        Children::For {
+            expr_b,
+            expr_a,
+            childrens,
+        } => {
+            v.visit_expr(expr_b);
+            v.visit_expr(expr_a);
+            v.visit_childrens(childrens);
+        }
+        Children::Html {
+            tag,
+            attrs,
+            childrens,
+        } => {
+            v.visit_ident(tag);
+            v.visit_attributes(attrs);
+            if let HtmlChildrens::Childrens(childrens) = childrens {
+                v.visit_childrens(childrens);
+            }
+        }
+        Children::Match { expr, arms } => {
+            v.visit_expr(expr);
+            for arm in arms {
+                v.visit_match_arm(arm);
+            }
+        }
+        Children::Outlet { name } => v.visit_ident(name),
+        Children::Portal { name, childrens } => {
+            v.visit_ident(name);
+            v.visit_childrens(childrens);
+        }
+        Children::Provide { expr, childrens } => {
+            v.visit_expr(expr);
+            v.visit_childrens(childrens);
+        }
+        Children::Scope { childrens } => v.visit_childrens(childrens),
+        Children::Script { text, attrs, .. } | Children::Style { text, attrs, .. } => {
+            v.visit_attributes(attrs);
+            if let Some(text) = text {
+                v.visit_text(text);
+            }
+        }
+        Children::Include { text, .. } => v.visit_text(text),
+    }
+}
+
+pub fn visit_attributes<V: Visit + ?Sized>(v: &mut V, attrs: &Attributes) {
+    for (key, value) in &attrs.0 {
+        match key {
+            AttributeKey::Ident(ident) => v.visit_ident(ident),
+            AttributeKey::LitStr(text) => v.visit_text(text),
+            #[allow(unreachable_patterns)]
+            _ => {}
+        }
+        match value {
+            Some(AttributeValue::LitStr(text)) => v.visit_text(text),
+            Some(AttributeValue::Expr(expr)) => v.visit_expr(expr),
+            None => {}
+        }
+    }
+}
+
+pub fn visit_match_arm<V: Visit + ?Sized>(v: &mut V, arm: &CustomMatchArm) {
+    v.visit_pat(&arm.pat);
+    if let Some((_, guard)) = &arm.guard {
+        v.visit_expr(guard);
+    }
+    v.visit_childrens(&arm.body);
+}